@@ -0,0 +1,109 @@
+//! Compares generating the summary of a large synthetic book on one thread
+//! and on the default thread pool.
+//!
+//! Run with `cargo bench`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
+
+use mdbook_auto_gen_summary::auto_gen_summary::config::AutoGenConfig;
+use mdbook_auto_gen_summary::auto_gen_summary::gen_summary;
+
+const SECTIONS: usize = 20;
+const CHAPTERS_PER_SECTION: usize = 10;
+const PAGES_PER_CHAPTER: usize = 15;
+const ITERATIONS: u32 = 10;
+
+fn create_book(source_dir: &Path) {
+    fs::create_dir_all(source_dir).unwrap();
+    fs::write(source_dir.join("README.md"), "# Book\n").unwrap();
+
+    for section in 0..SECTIONS {
+        let section_dir = source_dir.join(format!("section-{}", section));
+        fs::create_dir_all(&section_dir).unwrap();
+        fs::write(
+            section_dir.join("README.md"),
+            format!("# Section {}\n", section),
+        )
+        .unwrap();
+
+        for chapter in 0..CHAPTERS_PER_SECTION {
+            let chapter_dir = section_dir.join(format!("chapter-{}", chapter));
+            fs::create_dir_all(&chapter_dir).unwrap();
+            fs::write(
+                chapter_dir.join("README.md"),
+                format!("# Chapter {}\n", chapter),
+            )
+            .unwrap();
+
+            for page in 0..PAGES_PER_CHAPTER {
+                let content = format!(
+                    "---\ndate: 2020-01-{:02}\n---\n\n# Page {}\n\n{}",
+                    page % 28 + 1,
+                    page,
+                    "Lorem ipsum dolor sit amet.\n".repeat(200)
+                );
+                fs::write(chapter_dir.join(format!("page-{}.md", page)), content).unwrap();
+            }
+        }
+    }
+}
+
+/// Generates the summary `ITERATIONS` times on a pool of `threads` threads
+/// (0 for the default) and returns the number of threads, the average time
+/// and the summary.
+fn bench(source_dir: &Path, config: &AutoGenConfig, threads: usize) -> (usize, Duration, String) {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap();
+    let summary_path = source_dir.join("SUMMARY.md");
+
+    let mut total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        // Written again every time, like after a change to the book
+        let _ = fs::remove_file(&summary_path);
+
+        let start = Instant::now();
+        pool.install(|| gen_summary(source_dir, config)).unwrap();
+        total += start.elapsed();
+    }
+
+    (
+        pool.current_num_threads(),
+        total / ITERATIONS,
+        fs::read_to_string(summary_path).unwrap(),
+    )
+}
+
+fn main() {
+    let source_dir: PathBuf =
+        std::env::temp_dir().join(format!("auto-gen-summary-bench-{}", process::id()));
+    create_book(&source_dir);
+
+    let mut config = AutoGenConfig::new();
+    config.first_line_as_link_text = true;
+    config.index_first_line_as_directory_link_text = true;
+
+    let (_, sequential, sequential_summary) = bench(&source_dir, &config, 1);
+    let (threads, parallel, parallel_summary) = bench(&source_dir, &config, 0);
+
+    fs::remove_dir_all(&source_dir).unwrap();
+
+    assert_eq!(
+        sequential_summary, parallel_summary,
+        "The summaries generated on one and on many threads differ"
+    );
+
+    let pages = SECTIONS * CHAPTERS_PER_SECTION * PAGES_PER_CHAPTER;
+    println!("gen_summary of {} pages:", pages);
+    println!("  1 thread:  {:?}", sequential);
+    println!(
+        "  {} threads: {:?} ({:.1}x)",
+        threads,
+        parallel,
+        sequential.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
@@ -1,17 +1,85 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-use anyhow::Error;
+use anyhow::{Context, Error};
 use mdbook::Config;
+use serde::de::{Deserialize, Deserializer, Error as _};
+use serde_derive::Deserialize;
+use serde_json::json;
+use toml::value::{Table, Value};
 
-use crate::auto_gen_summary::PREPROCESSOR_NAME;
+use crate::auto_gen_summary::{relative_key, PREPROCESSOR_NAME, SUMMARY_FILE};
 
 const README_FILE: &str = "README.md";
+/// The name of mdbook's config file
+const BOOK_CONFIG_FILE: &str = "book.toml";
+/// The files that are never listed in the summary by default: summaries of
+/// vendored books and partials such as `_header.md`
+const DEFAULT_RESERVED_FILES: &[&str] = &[SUMMARY_FILE, "_*.md"];
+
+/// Name of the file that holds the configuration overrides for a directory
+/// and all of its subdirectories.
+pub const DIRECTORY_CONFIG_FILE: &str = ".auto-gen-summary.toml";
 
 const OPT_FIRST_LINE_AS_LINK: &str = "first-line-as-link-text";
 const OPT_INDEX_FIRST_LINE_AS_DIRECTORY_LINK: &str = "index-first-line-as-directory-link-text";
 const OPT_DIR_WITHOUT_INDEX_BEHAVIOR: &str = "directory-without-index-behavior";
 const OPT_DIRECTORY_INDEX_NAMES: &str = "directory-index-names";
+const OPT_DUPLICATE_INDEX_BEHAVIOR: &str = "duplicate-index-behavior";
+pub(crate) const OPT_RESERVED_FILES: &str = "reserved-files";
+const OPT_COLLAPSE_SINGLE_CHILD_DIRECTORIES: &str = "collapse-single-child-directories";
+const OPT_FLATTEN_DIRECTORIES: &str = "flatten-directories";
+const OPT_MAX_DEPTH: &str = "max-depth";
+const OPT_MAX_DEPTH_BEHAVIOR: &str = "max-depth-behavior";
+const OPT_SORT_BY: &str = "sort-by";
+const OPT_SORT_REVERSE: &str = "sort-reverse";
+const OPT_SORT_GROUP: &str = "sort-group";
+pub(crate) const OPT_TITLES: &str = "titles";
+pub(crate) const OPT_ORDER: &str = "order";
+pub(crate) const OPT_DRAFTS: &str = "drafts";
+const OPT_LANGUAGES: &str = "languages";
+const OPT_LANGUAGE: &str = "language";
+pub(crate) const OPT_EXCLUDE: &str = "exclude";
+const OPT_DRAFT_BEHAVIOR: &str = "draft-behavior";
+const OPT_HIDDEN_FILES: &str = "hidden-files";
+const OPT_RENDERER: &str = "renderer";
+const OPT_MISSING_TRANSLATION_BEHAVIOR: &str = "missing-translation-behavior";
+const OPT_EXPORT_FILE: &str = "export-file";
+const OPT_CACHE_FILE: &str = "cache-file";
+const OPT_OUTPUT: &str = "output";
+const OPT_EXTRA_ROOTS: &str = "extra-roots";
+const OPT_EXTRA_ENTRIES: &str = "extra-entries";
+const OPT_STRICT: &str = "strict";
+
+/// The prefix of the environment variables that override options
+const ENV_PREFIX: &str = "AUTO_GEN_SUMMARY_";
+
+/// The keys that apply to the whole book, which directory config files can't
+/// set
+const BOOK_LEVEL_KEYS: [&str; 14] = [
+    OPT_COLLAPSE_SINGLE_CHILD_DIRECTORIES,
+    OPT_FLATTEN_DIRECTORIES,
+    OPT_MAX_DEPTH,
+    OPT_MAX_DEPTH_BEHAVIOR,
+    OPT_LANGUAGES,
+    OPT_LANGUAGE,
+    OPT_MISSING_TRANSLATION_BEHAVIOR,
+    OPT_RENDERER,
+    OPT_EXPORT_FILE,
+    OPT_CACHE_FILE,
+    OPT_OUTPUT,
+    OPT_EXTRA_ROOTS,
+    OPT_DRAFT_BEHAVIOR,
+    OPT_EXTRA_ENTRIES,
+];
+
+/// The keys of a preprocessor's table in `book.toml` that are read by mdbook
+const MDBOOK_KEYS: [&str; 5] = ["command", "renderers", "before", "after", "optional"];
 
+#[derive(Clone)]
 pub struct AutoGenConfig {
     /// Whether the first line of the markdown file should be used
     /// as the file's title. If false, the title is name of the file.
@@ -22,11 +90,22 @@ pub struct AutoGenConfig {
     /// Default: false
     pub first_line_as_link_text: bool,
 
-    /// The names of the files that can serve as an index for a directory.
-    /// For example, "README.md" or "index.md".
+    /// The names of the files that can serve as an index for a directory,
+    /// in order of priority. For example, "README.md" or "index.md". If a
+    /// directory has more than one of them, the first one in this list is
+    /// used as the index.
     ///
-    /// Default: { "README.md" }
-    pub directory_index_names: HashSet<String>,
+    /// Default: [ "README.md" ]
+    pub directory_index_names: Vec<String>,
+
+    /// What to do with the index files of a directory that are not used
+    /// because a file with a higher priority name is present?
+    ///
+    /// Options: List them as regular chapters, drop them silently, or drop
+    /// them with a warning.
+    ///
+    /// Default: Warn
+    pub duplicate_index_behavior: DuplicateIndexBehavior,
 
     /// Whether the first line of the directory index markdown file
     /// should be used as the directory's title. If false, the title
@@ -51,6 +130,199 @@ pub struct AutoGenConfig {
     ///
     /// Default: "README.md"
     pub generated_directory_index_name: String,
+
+    /// Patterns of markdown files that are never listed in the summary, at
+    /// any depth. `*` matches any characters except `/` and `?` matches one
+    /// of them. Patterns without a `/` are matched against the file name,
+    /// others against the path relative to the source directory (or
+    /// language root). For example, `"_*.md"` or `"vendor/*/LICENSE.md"`.
+    ///
+    /// Default: [ "SUMMARY.md", "_*.md" ]
+    pub reserved_files: Vec<String>,
+
+    /// Whether chains of directories that only contain a single
+    /// subdirectory should be merged into one entry. For example,
+    /// `reference/v1/http/` becomes a single `reference/v1/http` entry.
+    ///
+    /// Only read from the book-level config.
+    ///
+    /// Default: false
+    pub collapse_single_child_directories: bool,
+
+    /// Directories (relative to the source directory) whose children are
+    /// hoisted into the parent directory. The index file of a flattened
+    /// directory is listed as a regular chapter.
+    ///
+    /// Only read from the book-level config.
+    ///
+    /// Default: []
+    pub flatten_directories: Vec<String>,
+
+    /// The maximum number of nesting levels shown in the summary. A value
+    /// of 1 only shows the top-level entries.
+    ///
+    /// Only read from the book-level config.
+    ///
+    /// Default: None (no limit)
+    pub max_depth: Option<usize>,
+
+    /// What to do with entries deeper than `max_depth`?
+    ///
+    /// Options: Omit the entries from the summary, flatten them into their
+    /// deepest visible ancestor, or build them as hidden pages.
+    ///
+    /// Default: Omit
+    pub max_depth_behavior: MaxDepthBehavior,
+
+    /// How the entries in a directory are sorted.
+    ///
+    /// Default: by path, not reversed, files and directories mixed
+    pub sort_order: SortOrder,
+
+    /// Titles to use instead of the generated titles, keyed by the path of
+    /// the file or directory relative to the source directory (or language
+    /// root). For example, `{ "guide" = "User Guide" }`.
+    ///
+    /// Default: {}
+    pub titles: HashMap<String, String>,
+
+    /// Names of the files, subdirectories and drafts of a directory that are
+    /// listed first, in this order. The other entries follow, sorted by
+    /// `sort_order`.
+    ///
+    /// Only applies to the directory whose config file sets it (or to the
+    /// source directory if set in the book-level config).
+    ///
+    /// Default: []
+    pub order: Vec<String>,
+
+    /// Titles of draft chapters added to a directory.
+    ///
+    /// Only applies to the directory whose config file sets it (or to the
+    /// source directory if set in the book-level config).
+    ///
+    /// Default: []
+    pub drafts: Vec<String>,
+
+    /// Subdirectories of the source directory that are each the root of a
+    /// translation of the book. A `SUMMARY.md` is generated in every
+    /// language root. The first language is the reference that the other
+    /// languages are compared against.
+    ///
+    /// Only read from the book-level config.
+    ///
+    /// Default: [] (the book is not split by language)
+    pub languages: Vec<String>,
+
+    /// Options that only apply to one language root, keyed by language.
+    ///
+    /// Default: {}
+    pub language_configs: HashMap<String, Table>,
+
+    /// What to do with pages of the reference language that are missing in
+    /// another language?
+    ///
+    /// Default: Warn
+    pub missing_translation_behavior: MissingTranslationBehavior,
+
+    /// Patterns of files and directories that are left out of the summary,
+    /// with everything they contain. Patterns are matched like
+    /// `reserved_files`.
+    ///
+    /// Default: []
+    pub exclude: Vec<String>,
+
+    /// What to do with draft chapters?
+    ///
+    /// Only read from the book-level config.
+    ///
+    /// Default: Keep
+    pub draft_behavior: DraftBehavior,
+
+    /// Patterns of files that are built but not listed in the summary, with
+    /// everything under them. Patterns are matched like `reserved_files`. A
+    /// page can also be hidden with `hidden: true` in its front matter.
+    ///
+    /// Default: ["*.hidden.md"]
+    pub hidden_files: Vec<String>,
+
+    /// Options that only apply when the book is built for one renderer,
+    /// keyed by the name of the renderer (ex. `html` or `epub`). The summary
+    /// of a renderer is only used to build the book in memory; the written
+    /// `SUMMARY.md` doesn't use these options.
+    ///
+    /// Default: {}
+    pub renderer_configs: HashMap<String, Table>,
+
+    /// A file (relative to the book root) that the preprocessor writes the
+    /// table of contents to. The format is picked from the extension:
+    /// `json`, `yaml`/`yml`, or `toml`.
+    ///
+    /// Only read from the book-level config.
+    ///
+    /// Default: None (no export)
+    pub export_file: Option<PathBuf>,
+
+    /// A file (relative to the book root) where the titles and front matter
+    /// of the markdown files are cached between runs. Only files whose
+    /// modification time or size changed are read again.
+    ///
+    /// Only read from the book-level config.
+    ///
+    /// Default: None (no cache)
+    pub cache_file: Option<PathBuf>,
+
+    /// The file (relative to the book root) the summary is written to.
+    /// Links in the summary are still relative to the source directory.
+    /// Can't be used with `languages`.
+    ///
+    /// Only read from the book-level config.
+    ///
+    /// Default: None (`SUMMARY.md` in the source directory)
+    pub output: Option<PathBuf>,
+
+    /// Directories outside the source directory whose files are listed in
+    /// the summary, each as if it were a subdirectory of the source
+    /// directory. Can't be used with `languages`.
+    ///
+    /// Only read from the book-level config.
+    ///
+    /// Default: []
+    pub extra_roots: Vec<ExtraRoot>,
+
+    /// Entries added to the summary that the generator doesn't find on
+    /// disk, such as links to other websites.
+    ///
+    /// Only read from the book-level config.
+    ///
+    /// Default: []
+    pub extra_entries: Vec<ExtraEntry>,
+
+    /// Whether unknown keys, values of the wrong type and invalid values in
+    /// the config are errors. If false, they are ignored with a warning.
+    ///
+    /// Default: true
+    pub strict: bool,
+
+    /// Whether walking the source directory must not create any files.
+    /// Directories that would get a stub index file are treated as drafts
+    /// instead. Set by commands that only report on the book; not read from
+    /// the config.
+    ///
+    /// Default: false
+    pub read_only: bool,
+
+    /// The directory that the paths in the config tables of the book are
+    /// relative to. Set by `from_book`; not read from the config.
+    ///
+    /// Default: "" (the current directory)
+    pub book_root: PathBuf,
+}
+
+impl Default for AutoGenConfig {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AutoGenConfig {
@@ -59,12 +331,40 @@ impl AutoGenConfig {
             first_line_as_link_text: false,
             index_first_line_as_directory_link_text: false,
             directory_without_index_behavior: DirectoryWithoutIndexBehavior::Ignore,
-            directory_index_names: {
-                let mut s = HashSet::new();
-                s.insert(String::from(README_FILE));
-                s
-            },
+            directory_index_names: vec![String::from(README_FILE)],
+            duplicate_index_behavior: DuplicateIndexBehavior::Warn,
             generated_directory_index_name: String::from(README_FILE),
+            reserved_files: DEFAULT_RESERVED_FILES
+                .iter()
+                .map(|pattern| String::from(*pattern))
+                .collect(),
+            collapse_single_child_directories: false,
+            flatten_directories: Vec::new(),
+            max_depth: None,
+            max_depth_behavior: MaxDepthBehavior::Omit,
+            sort_order: SortOrder {
+                key: SortKey::Path,
+                reverse: false,
+                group: SortGroup::Mixed,
+            },
+            titles: HashMap::new(),
+            order: Vec::new(),
+            drafts: Vec::new(),
+            languages: Vec::new(),
+            language_configs: HashMap::new(),
+            missing_translation_behavior: MissingTranslationBehavior::Warn,
+            exclude: Vec::new(),
+            draft_behavior: DraftBehavior::Keep,
+            hidden_files: vec![String::from("*.hidden.md")],
+            renderer_configs: HashMap::new(),
+            export_file: None,
+            cache_file: None,
+            output: None,
+            extra_roots: Vec::new(),
+            extra_entries: Vec::new(),
+            strict: true,
+            read_only: false,
+            book_root: PathBuf::new(),
         }
     }
 
@@ -75,72 +375,957 @@ impl AutoGenConfig {
             return Ok(());
         };
 
-        if let Some(v) = cfg.get(OPT_FIRST_LINE_AS_LINK) {
-            self.first_line_as_link_text = v.as_bool().unwrap_or(false);
-        }
+        let book_root = self.book_root.clone();
+        self.apply_table(cfg, &book_root)
+    }
 
-        if let Some(v) = cfg.get(OPT_INDEX_FIRST_LINE_AS_DIRECTORY_LINK) {
-            self.index_first_line_as_directory_link_text = v.as_bool().unwrap_or(false);
-        }
+    /// Builds the config of the book at `book_root` from its mdbook config,
+    /// then applies the environment variable overrides. The paths set in the
+    /// mdbook config, including its `renderer` and `language` tables, are
+    /// resolved against `book_root`.
+    pub fn from_book(book_root: &Path, mdbook_config: &Config) -> Result<AutoGenConfig, Error> {
+        let mut config = AutoGenConfig::new();
+        config.book_root = PathBuf::from(book_root);
+        config.apply_config(mdbook_config).with_context(|| {
+            format!(
+                "Invalid config in '{}'",
+                book_root.join(BOOK_CONFIG_FILE).display()
+            )
+        })?;
+        config.apply_env()?;
 
-        if let Some(v) = cfg.get(OPT_DIR_WITHOUT_INDEX_BEHAVIOR) {
-            let Some(v) = v.as_str() else {
-                anyhow::bail!(
-                    "Config key '{}' must be a string",
-                    OPT_DIR_WITHOUT_INDEX_BEHAVIOR
-                );
+        Ok(config)
+    }
+
+    /// Applies the options set in `AUTO_GEN_SUMMARY_*` environment variables.
+    /// The rest of the variable name is the key of the option in upper case,
+    /// with `_` instead of `-` (ex. `AUTO_GEN_SUMMARY_SORT_BY` for `sort-by`).
+    ///
+    /// Values are read by the type of the option: booleans and integers are
+    /// parsed, strings are used as is, and lists and tables are read as TOML
+    /// values (ex. `["a", "b"]`). Variables that don't name an option are
+    /// ignored with a warning.
+    pub fn apply_env(&mut self) -> Result<(), Error> {
+        let mut table = Table::new();
+
+        for (name, value) in std::env::vars_os() {
+            let (Ok(name), Ok(value)) = (name.into_string(), value.into_string()) else {
+                continue;
             };
-            let Some(v) = DirectoryWithoutIndexBehavior::from_str(v) else {
-                anyhow::bail!(
-                    "Config key '{}' must be one of 'ignore', 'draft', or 'generate-stub-index'",
-                    OPT_DIR_WITHOUT_INDEX_BEHAVIOR
+            let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+
+            let key = key.to_lowercase().replace('_', "-");
+            let Some(option) = OPTIONS.iter().find(|option| option.key == key) else {
+                eprintln!(
+                    "Warn: Ignoring the environment variable '{}': '{}' is not an option",
+                    name, key
                 );
+                continue;
             };
-            self.directory_without_index_behavior = v;
+            table.insert(key, option.kind.env_value(value));
         }
 
-        if let Some(v) = cfg.get(OPT_DIRECTORY_INDEX_NAMES) {
-            let mut directory_index_names = HashSet::new();
-            let mut generated_directory_index_name = None;
+        if table.is_empty() {
+            return Ok(());
+        }
 
-            let Some(v) = v.as_array() else {
+        // Paths in environment variables are relative to the current
+        // directory
+        self.apply_table(&table, Path::new("")).with_context(|| {
+            format!(
+                "Invalid config in the {}* environment variables",
+                ENV_PREFIX
+            )
+        })
+    }
+
+    /// Returns the config to use for `dir` if the directory contains a
+    /// directory config file. The options in the file are merged over this
+    /// config, which is inherited from the parent directory.
+    ///
+    /// Returns `None` if the directory has no config file.
+    ///
+    /// Keys that apply to the whole book are errors, or are ignored with a
+    /// warning if `strict` is false.
+    pub fn for_directory(&self, dir: &Path) -> Result<Option<AutoGenConfig>, Error> {
+        let config_file_path = dir.join(DIRECTORY_CONFIG_FILE);
+        if !config_file_path.is_file() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&config_file_path)
+            .with_context(|| format!("Failed to read '{}'", config_file_path.display()))?;
+        let mut table: Table = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse '{}'", config_file_path.display()))?;
+
+        let strict = table
+            .get(OPT_STRICT)
+            .and_then(Value::as_bool)
+            .unwrap_or(self.strict);
+        for key in BOOK_LEVEL_KEYS.iter() {
+            if table.remove(*key).is_none() {
+                continue;
+            }
+            if strict {
                 anyhow::bail!(
-                    "Config key '{}' must be an array.",
-                    OPT_DIRECTORY_INDEX_NAMES
+                    "Config key '{}' in '{}' is only read from the book-level config. Set '{} = false' to ignore it",
+                    key,
+                    config_file_path.display(),
+                    OPT_STRICT
                 );
-            };
+            }
+            eprintln!(
+                "Warn: Ignoring config key '{}' in '{}': it is only read from the book-level config",
+                key,
+                config_file_path.display()
+            );
+        }
+
+        let mut config = self.clone();
+        config.order = Vec::new();
+        config.drafts = Vec::new();
+        config
+            .apply_table(&table, &self.book_root)
+            .with_context(|| format!("Invalid config in '{}'", config_file_path.display()))?;
+
+        Ok(Some(config))
+    }
+
+    /// Returns the config for the root of `language`, with the options of
+    /// the language's table merged over this config.
+    pub fn for_language(&self, language: &str) -> Result<AutoGenConfig, Error> {
+        let mut config = self.clone();
+
+        if let Some(table) = self.language_configs.get(language) {
+            config
+                .apply_table(table, &self.book_root)
+                .with_context(|| format!("Invalid config for language '{}'", language))?;
+        }
+
+        Ok(config)
+    }
+
+    /// Returns the config for building the book for `renderer`, with the
+    /// options of the renderer's table merged over this config.
+    ///
+    /// Returns `None` if the renderer has no table.
+    pub fn for_renderer(&self, renderer: &str) -> Result<Option<AutoGenConfig>, Error> {
+        let Some(table) = self.renderer_configs.get(renderer) else {
+            return Ok(None);
+        };
+
+        let mut config = self.clone();
+        config
+            .apply_table(table, &self.book_root)
+            .with_context(|| format!("Invalid config for renderer '{}'", renderer))?;
+
+        Ok(Some(config))
+    }
+
+    /// Returns the language whose summary the preprocessor builds the book
+    /// from: `book_language` (the `book.language` option) if it is one of
+    /// the configured languages, otherwise the first language.
+    ///
+    /// Returns `None` if the book is not split by language.
+    pub fn book_language(&self, book_language: Option<&str>) -> Option<&String> {
+        self.languages
+            .iter()
+            .find(|language| Some(language.as_str()) == book_language)
+            .or_else(|| self.languages.first())
+    }
+
+    /// Returns the options of this config as the preprocessor's table in
+    /// `book.toml`. Options that are not set and empty tables are left out.
+    pub fn to_table(&self) -> Table {
+        OPTIONS
+            .iter()
+            .filter_map(|option| Some((String::from(option.key), (option.value)(self)?)))
+            .collect()
+    }
+
+    /// Returns the path of the summary generated for `source_dir`
+    pub fn summary_path(&self, source_dir: &Path) -> PathBuf {
+        match &self.output {
+            Some(output) => output.clone(),
+            None => source_dir.join(SUMMARY_FILE),
+        }
+    }
+
+    /// Returns the file name of the generated summary. A file with this name
+    /// at the top of the source directory is never listed in the summary.
+    pub fn summary_file_name(&self) -> OsString {
+        self.output
+            .as_ref()
+            .and_then(|output| output.file_name())
+            .map(OsString::from)
+            .unwrap_or_else(|| OsString::from(SUMMARY_FILE))
+    }
+
+    /// Returns whether the file at `path` matches one of the
+    /// `reserved_files` patterns.
+    pub fn is_reserved_file(&self, root_dir: &Path, path: &Path) -> bool {
+        matches_any_pattern(&self.reserved_files, root_dir, path)
+    }
+
+    /// Returns whether the file or directory at `path` matches one of the
+    /// `exclude` patterns.
+    pub fn is_excluded(&self, root_dir: &Path, path: &Path) -> bool {
+        matches_any_pattern(&self.exclude, root_dir, path)
+    }
+
+    /// Returns whether the file at `path` matches one of the `hidden_files`
+    /// patterns.
+    pub fn is_hidden_file(&self, root_dir: &Path, path: &Path) -> bool {
+        matches_any_pattern(&self.hidden_files, root_dir, path)
+    }
+
+    /// Apply the options found in a table of options, such as the
+    /// preprocessor's table in `book.toml` or a directory config file.
+    fn apply_table(&mut self, cfg: &Table, base_dir: &Path) -> Result<(), Error> {
+        let strict = table_strict(cfg, self.strict)?;
+        let cfg = ConfigTable::parse(cfg, strict)?;
+        cfg.check_sub_tables(strict)?;
+
+        if let Some(v) = cfg.strict {
+            self.strict = v;
+        }
+
+        if let Some(v) = cfg.first_line_as_link_text {
+            self.first_line_as_link_text = v;
+        }
+
+        if let Some(v) = cfg.index_first_line_as_directory_link_text {
+            self.index_first_line_as_directory_link_text = v;
+        }
+
+        if let Some(v) = cfg.directory_without_index_behavior {
+            if let Some(v) = parse_value::<DirectoryWithoutIndexBehavior>(
+                &format!("config key '{}'", OPT_DIR_WITHOUT_INDEX_BEHAVIOR),
+                &v,
+                strict,
+            )? {
+                self.directory_without_index_behavior = v;
+            }
+        }
+
+        if let Some(v) = cfg.directory_index_names {
+            let mut directory_index_names = Vec::new();
+            let mut generated_directory_index_name = None;
+
             for item in v {
-                let Some(item) = item.as_str() else {
-                    anyhow::bail!(
-                        "Item in array for config key {} is not a string.",
-                        OPT_DIRECTORY_INDEX_NAMES
-                    );
-                };
-                directory_index_names.insert(String::from(item));
+                if !directory_index_names.contains(&item) {
+                    directory_index_names.push(item.clone());
+                }
 
                 if generated_directory_index_name.is_none() {
-                    generated_directory_index_name = Some(String::from(item));
+                    generated_directory_index_name = Some(item);
                 }
             }
 
-            let Some(generated_directory_index_name) = generated_directory_index_name else {
+            if let Some(generated_directory_index_name) = generated_directory_index_name {
+                self.generated_directory_index_name = generated_directory_index_name;
+                self.directory_index_names = directory_index_names;
+            } else if strict {
                 anyhow::bail!(
                     "Config key {} must not be empty.",
                     OPT_DIRECTORY_INDEX_NAMES
                 )
-            };
+            } else {
+                eprintln!(
+                    "Warn: Ignoring config key '{}': it must not be empty",
+                    OPT_DIRECTORY_INDEX_NAMES
+                );
+            }
+        }
+
+        if let Some(v) = cfg.duplicate_index_behavior {
+            if let Some(v) = parse_value::<DuplicateIndexBehavior>(
+                &format!("config key '{}'", OPT_DUPLICATE_INDEX_BEHAVIOR),
+                &v,
+                strict,
+            )? {
+                self.duplicate_index_behavior = v;
+            }
+        }
+
+        if let Some(v) = cfg.reserved_files {
+            self.reserved_files = v;
+        }
+
+        if let Some(v) = cfg.collapse_single_child_directories {
+            self.collapse_single_child_directories = v;
+        }
+
+        if let Some(v) = cfg.flatten_directories {
+            self.flatten_directories = v;
+        }
+
+        if let Some(v) = cfg.max_depth {
+            self.max_depth = Some(v);
+        }
+
+        if let Some(v) = cfg.max_depth_behavior {
+            if let Some(v) = parse_value::<MaxDepthBehavior>(
+                &format!("config key '{}'", OPT_MAX_DEPTH_BEHAVIOR),
+                &v,
+                strict,
+            )? {
+                self.max_depth_behavior = v;
+            }
+        }
+
+        if let Some(v) = cfg.sort_by {
+            if let Some(v) =
+                parse_value::<SortKey>(&format!("config key '{}'", OPT_SORT_BY), &v, strict)?
+            {
+                self.sort_order.key = v;
+            }
+        }
+
+        if let Some(v) = cfg.sort_reverse {
+            self.sort_order.reverse = v;
+        }
+
+        if let Some(v) = cfg.sort_group {
+            if let Some(v) =
+                parse_value::<SortGroup>(&format!("config key '{}'", OPT_SORT_GROUP), &v, strict)?
+            {
+                self.sort_order.group = v;
+            }
+        }
+
+        if let Some(v) = cfg.titles {
+            for (path, title) in v {
+                self.titles
+                    .insert(path.trim_matches('/').to_string(), title);
+            }
+        }
+
+        if let Some(v) = cfg.order {
+            self.order = v;
+        }
+
+        if let Some(v) = cfg.drafts {
+            self.drafts = v;
+        }
 
-            self.generated_directory_index_name = generated_directory_index_name;
-            self.directory_index_names = directory_index_names;
+        if let Some(v) = cfg.languages {
+            self.languages = v;
+        }
+
+        if let Some(v) = cfg.language {
+            self.language_configs.extend(v);
+        }
+
+        if let Some(v) = cfg.exclude {
+            self.exclude = v;
+        }
+
+        if let Some(v) = cfg.draft_behavior {
+            if let Some(v) = parse_value::<DraftBehavior>(
+                &format!("config key '{}'", OPT_DRAFT_BEHAVIOR),
+                &v,
+                strict,
+            )? {
+                self.draft_behavior = v;
+            }
+        }
+
+        if let Some(v) = cfg.hidden_files {
+            self.hidden_files = v;
+        }
+
+        if let Some(v) = cfg.renderer {
+            self.renderer_configs.extend(v);
+        }
+
+        if let Some(v) = cfg.missing_translation_behavior {
+            if let Some(v) = parse_value::<MissingTranslationBehavior>(
+                &format!("config key '{}'", OPT_MISSING_TRANSLATION_BEHAVIOR),
+                &v,
+                strict,
+            )? {
+                self.missing_translation_behavior = v;
+            }
+        }
+
+        if let Some(v) = cfg.export_file {
+            self.export_file = Some(base_dir.join(v));
+        }
+
+        if let Some(v) = cfg.cache_file {
+            self.cache_file = Some(base_dir.join(v));
+        }
+
+        if let Some(v) = cfg.output {
+            self.output = Some(base_dir.join(v));
+        }
+
+        if let Some(v) = cfg.extra_roots {
+            self.extra_roots = v
+                .into_iter()
+                .map(|table| ExtraRoot::from_table(table, base_dir, strict))
+                .collect::<Result<_, _>>()?;
+        }
+
+        if let Some(v) = cfg.extra_entries {
+            self.extra_entries = v
+                .into_iter()
+                .map(|table| ExtraEntry::from_table(table, strict))
+                .collect::<Result<_, _>>()?;
         }
 
         Ok(())
     }
 }
 
+/// The type of the value of an option, as described in the JSON Schema
+enum OptionKind {
+    Boolean,
+    String,
+    Strings,
+    /// One of the given strings
+    OneOf(&'static [&'static str]),
+    /// An integer of at least 1
+    PositiveInteger,
+    /// A table of strings, keyed by path
+    Titles,
+    /// A table of option tables
+    Tables,
+    /// An array of tables, described by the JSON Schema of an item
+    TableArray(fn() -> serde_json::Value),
+}
+
+/// An option of the preprocessor's table in `book.toml`. `to_table` and
+/// `json_schema` are both built from `OPTIONS`.
+struct ConfigOption {
+    key: &'static str,
+    kind: OptionKind,
+    description: &'static str,
+    /// Returns the value of the option in `book.toml`, or `None` if it is
+    /// left out
+    value: fn(&AutoGenConfig) -> Option<Value>,
+}
+
+/// Every option of the preprocessor's table in `book.toml`
+const OPTIONS: &[ConfigOption] = &[
+    ConfigOption {
+        key: OPT_STRICT,
+        kind: OptionKind::Boolean,
+        description: "Whether unknown keys, values of the wrong type and invalid values are errors. Default: true",
+        value: |config| Some(Value::from(config.strict)),
+    },
+    ConfigOption {
+        key: OPT_FIRST_LINE_AS_LINK,
+        kind: OptionKind::Boolean,
+        description: "Use the first h1 heading of files as their title. Default: false",
+        value: |config| Some(Value::from(config.first_line_as_link_text)),
+    },
+    ConfigOption {
+        key: OPT_INDEX_FIRST_LINE_AS_DIRECTORY_LINK,
+        kind: OptionKind::Boolean,
+        description:
+            "Use the first h1 heading of index files as the title of their directory. Default: false",
+        value: |config| Some(Value::from(config.index_first_line_as_directory_link_text)),
+    },
+    ConfigOption {
+        key: OPT_DIR_WITHOUT_INDEX_BEHAVIOR,
+        kind: OptionKind::OneOf(DirectoryWithoutIndexBehavior::VALUES),
+        description: "Behavior of a directory without an index file. Default: \"ignore\"",
+        value: |config| Some(Value::from(config.directory_without_index_behavior.as_str())),
+    },
+    ConfigOption {
+        key: OPT_DIRECTORY_INDEX_NAMES,
+        kind: OptionKind::Strings,
+        description: "Names of the files used as the index of a directory, in order of priority. Default: [\"README.md\"]",
+        value: |config| Some(strings_value(&config.directory_index_names)),
+    },
+    ConfigOption {
+        key: OPT_DUPLICATE_INDEX_BEHAVIOR,
+        kind: OptionKind::OneOf(DuplicateIndexBehavior::VALUES),
+        description: "Behavior of index files not used because a higher priority index file exists. Default: \"warn\"",
+        value: |config| Some(Value::from(config.duplicate_index_behavior.as_str())),
+    },
+    ConfigOption {
+        key: OPT_RESERVED_FILES,
+        kind: OptionKind::Strings,
+        description: "Patterns of files that are never listed. Default: [\"SUMMARY.md\", \"_*.md\"]",
+        value: |config| Some(strings_value(&config.reserved_files)),
+    },
+    ConfigOption {
+        key: OPT_COLLAPSE_SINGLE_CHILD_DIRECTORIES,
+        kind: OptionKind::Boolean,
+        description:
+            "Merge chains of directories with a single subdirectory into one entry. Default: false",
+        value: |config| Some(Value::from(config.collapse_single_child_directories)),
+    },
+    ConfigOption {
+        key: OPT_FLATTEN_DIRECTORIES,
+        kind: OptionKind::Strings,
+        description: "Directories whose children are hoisted into the parent directory. Default: []",
+        value: |config| Some(strings_value(&config.flatten_directories)),
+    },
+    ConfigOption {
+        key: OPT_MAX_DEPTH,
+        kind: OptionKind::PositiveInteger,
+        description: "Maximum number of nesting levels shown in the summary. Default: no limit",
+        value: |config| config.max_depth.map(|max_depth| Value::from(max_depth as i64)),
+    },
+    ConfigOption {
+        key: OPT_MAX_DEPTH_BEHAVIOR,
+        kind: OptionKind::OneOf(MaxDepthBehavior::VALUES),
+        description: "Behavior of entries deeper than the maximum depth. Default: \"omit\"",
+        value: |config| Some(Value::from(config.max_depth_behavior.as_str())),
+    },
+    ConfigOption {
+        key: OPT_SORT_BY,
+        kind: OptionKind::OneOf(SortKey::VALUES),
+        description: "Value the entries in a directory are sorted by. Default: \"path\"",
+        value: |config| Some(Value::from(config.sort_order.key.as_str())),
+    },
+    ConfigOption {
+        key: OPT_SORT_REVERSE,
+        kind: OptionKind::Boolean,
+        description: "Reverse the sort order. Default: false",
+        value: |config| Some(Value::from(config.sort_order.reverse)),
+    },
+    ConfigOption {
+        key: OPT_SORT_GROUP,
+        kind: OptionKind::OneOf(SortGroup::VALUES),
+        description:
+            "Whether files and directories are sorted as separate groups. Default: \"mixed\"",
+        value: |config| Some(Value::from(config.sort_order.group.as_str())),
+    },
+    ConfigOption {
+        key: OPT_TITLES,
+        kind: OptionKind::Titles,
+        description: "Titles that replace the generated titles, keyed by path",
+        value: |config| {
+            non_empty_table(
+                config
+                    .titles
+                    .iter()
+                    .map(|(path, title)| (path.clone(), Value::from(title.as_str())))
+                    .collect(),
+            )
+        },
+    },
+    ConfigOption {
+        key: OPT_ORDER,
+        kind: OptionKind::Strings,
+        description: "Names of the entries listed first in the directory, in this order",
+        value: |config| Some(strings_value(&config.order)),
+    },
+    ConfigOption {
+        key: OPT_DRAFTS,
+        kind: OptionKind::Strings,
+        description: "Titles of draft chapters to add to the directory",
+        value: |config| Some(strings_value(&config.drafts)),
+    },
+    ConfigOption {
+        key: OPT_LANGUAGES,
+        kind: OptionKind::Strings,
+        description: "Language subdirectories that each get their own summary. Default: []",
+        value: |config| Some(strings_value(&config.languages)),
+    },
+    ConfigOption {
+        key: OPT_LANGUAGE,
+        kind: OptionKind::Tables,
+        description: "Options for the root of a language, keyed by language",
+        value: |config| {
+            non_empty_table(
+                config
+                    .language_configs
+                    .iter()
+                    .map(|(language, config)| (language.clone(), Value::Table(config.clone())))
+                    .collect(),
+            )
+        },
+    },
+    ConfigOption {
+        key: OPT_MISSING_TRANSLATION_BEHAVIOR,
+        kind: OptionKind::OneOf(MissingTranslationBehavior::VALUES),
+        description: "Behavior of pages missing in a language. Default: \"warn\"",
+        value: |config| Some(Value::from(config.missing_translation_behavior.as_str())),
+    },
+    ConfigOption {
+        key: OPT_EXCLUDE,
+        kind: OptionKind::Strings,
+        description:
+            "Patterns of files and directories that are left out of the summary. Default: []",
+        value: |config| Some(strings_value(&config.exclude)),
+    },
+    ConfigOption {
+        key: OPT_DRAFT_BEHAVIOR,
+        kind: OptionKind::OneOf(DraftBehavior::VALUES),
+        description: "Whether draft chapters are kept or left out. Default: \"keep\"",
+        value: |config| Some(Value::from(config.draft_behavior.as_str())),
+    },
+    ConfigOption {
+        key: OPT_HIDDEN_FILES,
+        kind: OptionKind::Strings,
+        description: "Patterns of files that are built but not listed in the summary. Default: [\"*.hidden.md\"]",
+        value: |config| Some(strings_value(&config.hidden_files)),
+    },
+    ConfigOption {
+        key: OPT_RENDERER,
+        kind: OptionKind::Tables,
+        description: "Options for building the book for a renderer, keyed by renderer",
+        value: |config| {
+            non_empty_table(
+                config
+                    .renderer_configs
+                    .iter()
+                    .map(|(renderer, config)| (renderer.clone(), Value::Table(config.clone())))
+                    .collect(),
+            )
+        },
+    },
+    ConfigOption {
+        key: OPT_EXPORT_FILE,
+        kind: OptionKind::String,
+        description: "File the table of contents is exported to, relative to the book root",
+        value: |config| config.export_file.as_deref().map(path_value),
+    },
+    ConfigOption {
+        key: OPT_CACHE_FILE,
+        kind: OptionKind::String,
+        description: "File the metadata of markdown files is cached in, relative to the book root",
+        value: |config| config.cache_file.as_deref().map(path_value),
+    },
+    ConfigOption {
+        key: OPT_OUTPUT,
+        kind: OptionKind::String,
+        description: "File the summary is written to, relative to the book root",
+        value: |config| config.output.as_deref().map(path_value),
+    },
+    ConfigOption {
+        key: OPT_EXTRA_ROOTS,
+        kind: OptionKind::TableArray(ExtraRoot::item_schema),
+        description: "Directories outside the source directory that are listed as subdirectories of it. Default: []",
+        value: |config| {
+            if config.extra_roots.is_empty() {
+                return None;
+            }
+            Some(Value::Array(
+                config
+                    .extra_roots
+                    .iter()
+                    .map(|extra_root| Value::Table(extra_root.to_table()))
+                    .collect(),
+            ))
+        },
+    },
+    ConfigOption {
+        key: OPT_EXTRA_ENTRIES,
+        kind: OptionKind::TableArray(ExtraEntry::item_schema),
+        description:
+            "Entries added to the summary, such as links to other websites. Default: []",
+        value: |config| {
+            if config.extra_entries.is_empty() {
+                return None;
+            }
+            Some(Value::Array(
+                config
+                    .extra_entries
+                    .iter()
+                    .map(|extra_entry| Value::Table(extra_entry.to_table()))
+                    .collect(),
+            ))
+        },
+    },
+];
+
+fn strings_value(items: &[String]) -> Value {
+    Value::Array(items.iter().cloned().map(Value::from).collect())
+}
+
+fn path_value(path: &Path) -> Value {
+    Value::from(path.to_string_lossy().to_string())
+}
+
+/// Returns `table` as a value, or `None` if it is empty
+fn non_empty_table(table: Table) -> Option<Value> {
+    if table.is_empty() {
+        None
+    } else {
+        Some(Value::Table(table))
+    }
+}
+
+impl OptionKind {
+    /// Returns the value of an option of this kind set in an environment
+    /// variable. Values that can't be read as this kind are returned as
+    /// strings, so the error names the expected type.
+    fn env_value(&self, value: String) -> Value {
+        match self {
+            OptionKind::Boolean => match value.as_str() {
+                "true" => Value::Boolean(true),
+                "false" => Value::Boolean(false),
+                _ => Value::String(value),
+            },
+            OptionKind::PositiveInteger => match value.parse::<i64>() {
+                Ok(number) => Value::Integer(number),
+                Err(_) => Value::String(value),
+            },
+            OptionKind::String | OptionKind::OneOf(_) => Value::String(value),
+            OptionKind::Strings
+            | OptionKind::Titles
+            | OptionKind::Tables
+            | OptionKind::TableArray(_) => toml::from_str::<Table>(&format!("value = {}", value))
+                .ok()
+                .and_then(|mut parsed| parsed.remove("value"))
+                .unwrap_or(Value::String(value)),
+        }
+    }
+
+    /// Returns the JSON Schema of a value of this kind
+    fn schema(&self) -> serde_json::Value {
+        match self {
+            OptionKind::Boolean => json!({ "type": "boolean" }),
+            OptionKind::String => json!({ "type": "string" }),
+            OptionKind::Strings => json!({ "type": "array", "items": { "type": "string" } }),
+            OptionKind::OneOf(values) => json!({ "type": "string", "enum": values }),
+            OptionKind::PositiveInteger => json!({ "type": "integer", "minimum": 1 }),
+            OptionKind::Titles => json!({
+                "type": "object",
+                "additionalProperties": { "type": "string" },
+            }),
+            OptionKind::Tables => json!({
+                "type": "object",
+                "additionalProperties": { "$ref": "#" },
+            }),
+            OptionKind::TableArray(item_schema) => json!({
+                "type": "array",
+                "items": item_schema(),
+            }),
+        }
+    }
+}
+
+/// The options of the preprocessor's table in `book.toml` or of a directory
+/// config file, with the types they must have
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct ConfigTable {
+    strict: Option<bool>,
+    first_line_as_link_text: Option<bool>,
+    index_first_line_as_directory_link_text: Option<bool>,
+    directory_without_index_behavior: Option<String>,
+    directory_index_names: Option<Vec<String>>,
+    duplicate_index_behavior: Option<String>,
+    reserved_files: Option<Vec<String>>,
+    collapse_single_child_directories: Option<bool>,
+    flatten_directories: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "positive_integer")]
+    max_depth: Option<usize>,
+    max_depth_behavior: Option<String>,
+    sort_by: Option<String>,
+    sort_reverse: Option<bool>,
+    sort_group: Option<String>,
+    titles: Option<BTreeMap<String, String>>,
+    order: Option<Vec<String>>,
+    drafts: Option<Vec<String>>,
+    languages: Option<Vec<String>>,
+    language: Option<BTreeMap<String, Table>>,
+    missing_translation_behavior: Option<String>,
+    exclude: Option<Vec<String>>,
+    draft_behavior: Option<String>,
+    hidden_files: Option<Vec<String>>,
+    renderer: Option<BTreeMap<String, Table>>,
+    export_file: Option<PathBuf>,
+    cache_file: Option<PathBuf>,
+    output: Option<PathBuf>,
+    extra_roots: Option<Vec<ExtraRootTable>>,
+    extra_entries: Option<Vec<ExtraEntryTable>>,
+}
+
+/// The options of one of the `extra-roots`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExtraRootTable {
+    path: PathBuf,
+    mount: Option<String>,
+    title: Option<String>,
+    position: Option<String>,
+}
+
+/// The options of one of the `extra-entries`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExtraEntryTable {
+    title: String,
+    url: Option<String>,
+    path: Option<PathBuf>,
+    position: Option<String>,
+    directory: Option<String>,
+}
+
+impl ConfigTable {
+    /// Checks the keys and the types of the values of `cfg`. Unknown keys
+    /// and values of the wrong type are errors if `strict` is true,
+    /// otherwise they are ignored with a warning.
+    fn parse(cfg: &Table, strict: bool) -> Result<ConfigTable, Error> {
+        // Keys that mdbook reads from the table of every preprocessor
+        let cfg: Table = cfg
+            .iter()
+            .filter(|(key, _)| !MDBOOK_KEYS.contains(&key.as_str()))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        if strict {
+            return Value::Table(cfg).try_into().map_err(|e| {
+                anyhow::anyhow!("{}. Set '{} = false' to ignore invalid keys", e, OPT_STRICT)
+            });
+        }
+
+        let mut valid = Table::new();
+        for (key, value) in cfg {
+            let mut single = Table::new();
+            single.insert(key.clone(), value.clone());
+            match Value::Table(single).try_into::<ConfigTable>() {
+                Ok(_) => {
+                    valid.insert(key, value);
+                }
+                Err(e) => eprintln!("Warn: Ignoring config key '{}': {}", key, e),
+            }
+        }
+
+        Ok(Value::Table(valid).try_into()?)
+    }
+
+    /// Checks the keys and the types of the `language` and `renderer`
+    /// tables, so mistakes in the table of a language or renderer that is
+    /// not built are errors too. Tables that are not strict are only checked
+    /// when they are applied, where invalid keys are ignored with a warning.
+    fn check_sub_tables(&self, strict: bool) -> Result<(), Error> {
+        let languages = self
+            .language
+            .iter()
+            .flatten()
+            .map(|(name, table)| (format!("language '{}'", name), table));
+        let renderers = self
+            .renderer
+            .iter()
+            .flatten()
+            .map(|(name, table)| (format!("renderer '{}'", name), table));
+
+        for (name, table) in languages.chain(renderers) {
+            if !table_strict(table, strict)? {
+                continue;
+            }
+            ConfigTable::parse(table, true)
+                .and_then(|table| table.check_sub_tables(true))
+                .with_context(|| format!("Invalid config for {}", name))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the `strict` option of `cfg`, or `default` if it is not set
+fn table_strict(cfg: &Table, default: bool) -> Result<bool, Error> {
+    match cfg.get(OPT_STRICT) {
+        Some(v) => match v.as_bool() {
+            Some(v) => Ok(v),
+            None => anyhow::bail!("Config key '{}' must be a boolean", OPT_STRICT),
+        },
+        None => Ok(default),
+    }
+}
+
+/// Deserializes an integer that must be at least 1
+fn positive_integer<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<usize>, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Integer(v) if v >= 1 => Ok(Some(v as usize)),
+        v => Err(D::Error::custom(format!(
+            "invalid value: {}, expected an integer of at least 1",
+            v
+        ))),
+    }
+}
+
+/// Returns a JSON Schema of the preprocessor's table in `book.toml`, which
+/// also describes directory config files and `language` tables.
+pub fn json_schema() -> serde_json::Value {
+    let mut properties = serde_json::Map::new();
+    for option in OPTIONS {
+        let mut schema = option.kind.schema();
+        schema["description"] = json!(option.description);
+        properties.insert(String::from(option.key), schema);
+    }
+    for key in MDBOOK_KEYS {
+        properties.insert(
+            String::from(key),
+            json!({ "description": "Read by mdbook" }),
+        );
+    }
+
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "[preprocessor.auto-gen-summary]",
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false,
+    })
+}
+
+/// Returns the root of the book that `path` belongs to: the closest
+/// directory that contains `book.toml`, starting at `path` and going up.
+pub fn find_book_root(path: &Path) -> Option<PathBuf> {
+    let path = fs::canonicalize(path).ok()?;
+
+    path.ancestors()
+        .find(|dir| dir.join(BOOK_CONFIG_FILE).is_file())
+        .map(PathBuf::from)
+}
+
+/// Returns whether `path` matches one of `patterns`. Patterns without a `/`
+/// are matched against the name of the file, others against its path
+/// relative to `root_dir`.
+fn matches_any_pattern(patterns: &[String], root_dir: &Path, path: &Path) -> bool {
+    if patterns.is_empty() {
+        return false;
+    }
+
+    let relative_path = relative_key(root_dir, path);
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    patterns.iter().any(|pattern| {
+        let text = if pattern.contains('/') {
+            &relative_path
+        } else {
+            &file_name
+        };
+        let pattern: Vec<char> = pattern.trim_matches('/').chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        matches_pattern(&pattern, &text)
+    })
+}
+
+/// Matches `text` against a pattern where `*` matches any characters except
+/// `/` and `?` matches one character except `/`.
+fn matches_pattern(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len())
+            .take_while(|i| *i == 0 || text[i - 1] != '/')
+            .any(|i| matches_pattern(rest, &text[i..])),
+        Some(('?', rest)) => {
+            text.first().is_some_and(|c| *c != '/') && matches_pattern(rest, &text[1..])
+        }
+        Some((c, rest)) => text.first() == Some(c) && matches_pattern(rest, &text[1..]),
+    }
+}
+
 /// Define the behavior for a directory with markdown files
 /// but no index markdown files found
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone)]
 pub enum DirectoryWithoutIndexBehavior {
     /// Ignore directory completely (default)
     Ignore,
@@ -151,12 +1336,851 @@ pub enum DirectoryWithoutIndexBehavior {
 }
 
 impl DirectoryWithoutIndexBehavior {
-    pub fn from_str(s: &str) -> Option<DirectoryWithoutIndexBehavior> {
+    /// The values the option accepts
+    pub const VALUES: &'static [&'static str] = &["ignore", "draft", "generate-stub-index"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DirectoryWithoutIndexBehavior::Ignore => "ignore",
+            DirectoryWithoutIndexBehavior::Draft => "draft",
+            DirectoryWithoutIndexBehavior::GenerateStubIndex => "generate-stub-index",
+        }
+    }
+}
+
+impl FromStr for DirectoryWithoutIndexBehavior {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<DirectoryWithoutIndexBehavior, Error> {
+        match s {
+            "ignore" => Ok(DirectoryWithoutIndexBehavior::Ignore),
+            "draft" => Ok(DirectoryWithoutIndexBehavior::Draft),
+            "generate-stub-index" => Ok(DirectoryWithoutIndexBehavior::GenerateStubIndex),
+            _ => anyhow::bail!(
+                "'{}' is not one of {}",
+                s,
+                quoted_values(DirectoryWithoutIndexBehavior::VALUES)
+            ),
+        }
+    }
+}
+
+/// Define the behavior for index files that are not used because the
+/// directory has an index file with a higher priority name
+#[derive(PartialEq, Clone)]
+pub enum DuplicateIndexBehavior {
+    /// List the files as regular chapters
+    Chapter,
+    /// Leave the files out of the summary
+    Drop,
+    /// Leave the files out of the summary and print a warning (default)
+    Warn,
+}
+
+impl DuplicateIndexBehavior {
+    /// The values the option accepts
+    pub const VALUES: &'static [&'static str] = &["chapter", "drop", "warn"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DuplicateIndexBehavior::Chapter => "chapter",
+            DuplicateIndexBehavior::Drop => "drop",
+            DuplicateIndexBehavior::Warn => "warn",
+        }
+    }
+}
+
+impl FromStr for DuplicateIndexBehavior {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<DuplicateIndexBehavior, Error> {
+        match s {
+            "chapter" => Ok(DuplicateIndexBehavior::Chapter),
+            "drop" => Ok(DuplicateIndexBehavior::Drop),
+            "warn" => Ok(DuplicateIndexBehavior::Warn),
+            _ => anyhow::bail!(
+                "'{}' is not one of {}",
+                s,
+                quoted_values(DuplicateIndexBehavior::VALUES)
+            ),
+        }
+    }
+}
+
+/// Define what happens to draft chapters
+#[derive(PartialEq, Clone)]
+pub enum DraftBehavior {
+    /// List the drafts in the summary (default)
+    Keep,
+    /// Leave the drafts out of the summary. The children of a draft
+    /// directory take its place.
+    Omit,
+}
+
+impl DraftBehavior {
+    /// The values the option accepts
+    pub const VALUES: &'static [&'static str] = &["keep", "omit"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DraftBehavior::Keep => "keep",
+            DraftBehavior::Omit => "omit",
+        }
+    }
+}
+
+impl FromStr for DraftBehavior {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<DraftBehavior, Error> {
+        match s {
+            "keep" => Ok(DraftBehavior::Keep),
+            "omit" => Ok(DraftBehavior::Omit),
+            _ => anyhow::bail!(
+                "'{}' is not one of {}",
+                s,
+                quoted_values(DraftBehavior::VALUES)
+            ),
+        }
+    }
+}
+
+/// A directory outside the source directory whose files are listed in the
+/// summary as if it were a subdirectory of the source directory
+#[derive(Clone)]
+pub struct ExtraRoot {
+    /// The directory, relative to the book root
+    pub path: PathBuf,
+    /// The name of the subdirectory of the source directory it is mounted
+    /// as. The preprocessor builds its pages under this directory.
+    ///
+    /// Default: the name of `path`
+    pub mount: String,
+    /// The title of its entry. If None, it gets the title of a directory.
+    pub title: Option<String>,
+    pub position: MountPosition,
+}
+
+impl ExtraRoot {
+    /// Reads one of the `extra-roots`. Its path is resolved against
+    /// `base_dir`.
+    fn from_table(
+        table: ExtraRootTable,
+        base_dir: &Path,
+        strict: bool,
+    ) -> Result<ExtraRoot, Error> {
+        let mount = match table.mount {
+            Some(mount) => mount,
+            None => match table.path.file_name() {
+                Some(name) => name.to_string_lossy().to_string(),
+                None => anyhow::bail!(
+                    "Config key '{}' has no 'mount' for '{}', which has no directory name",
+                    OPT_EXTRA_ROOTS,
+                    table.path.display()
+                ),
+            },
+        };
+        if mount.is_empty() || mount.contains(['/', '\\']) || mount == "." || mount == ".." {
+            anyhow::bail!(
+                "The 'mount' of '{}' in config key '{}' must be a single directory name",
+                table.path.display(),
+                OPT_EXTRA_ROOTS
+            );
+        }
+
+        let position = match &table.position {
+            Some(position) => parse_value::<MountPosition>(
+                &format!(
+                    "the 'position' of '{}' in config key '{}'",
+                    table.path.display(),
+                    OPT_EXTRA_ROOTS
+                ),
+                position,
+                strict,
+            )?,
+            None => None,
+        };
+
+        Ok(ExtraRoot {
+            path: base_dir.join(&table.path),
+            mount,
+            title: table.title,
+            position: position.unwrap_or(MountPosition::Sorted),
+        })
+    }
+
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table.insert(
+            String::from("path"),
+            Value::from(self.path.to_string_lossy().to_string()),
+        );
+        table.insert(String::from("mount"), Value::from(self.mount.clone()));
+        if let Some(title) = &self.title {
+            table.insert(String::from("title"), Value::from(title.clone()));
+        }
+        table.insert(
+            String::from("position"),
+            Value::from(self.position.as_str()),
+        );
+        table
+    }
+
+    /// Returns the JSON Schema of one of the `extra-roots`
+    fn item_schema() -> serde_json::Value {
+        let string = |description: &str| json!({ "type": "string", "description": description });
+
+        json!({
+            "type": "object",
+            "properties": {
+                "path": string("The directory, relative to the book root"),
+                "mount": string("Name of the subdirectory of the source directory it is mounted as. Default: the name of the directory"),
+                "title": string("Title of its entry. Default: the title of a directory"),
+                "position": {
+                    "type": "string",
+                    "enum": MountPosition::VALUES,
+                    "description": "Where its entry is listed in the source directory. Default: \"sorted\"",
+                },
+            },
+            "required": ["path"],
+            "additionalProperties": false,
+        })
+    }
+}
+
+/// An entry added to the summary that the generator doesn't find on disk
+#[derive(Clone)]
+pub struct ExtraEntry {
+    pub title: String,
+    pub target: EntryTarget,
+    pub position: EntryPosition,
+    /// The directory the entry is listed in, relative to the source
+    /// directory (or language root). If None, it is listed in the source
+    /// directory.
+    pub directory: Option<String>,
+}
+
+/// What an extra entry links to
+#[derive(Clone)]
+pub enum EntryTarget {
+    /// A URL outside the book. A URL without a scheme is relative to the
+    /// root of the built book.
+    Url(String),
+    /// A markdown file, relative to the source directory (or language root)
+    Path(PathBuf),
+}
+
+impl ExtraEntry {
+    fn from_table(table: ExtraEntryTable, strict: bool) -> Result<ExtraEntry, Error> {
+        let target = match (table.url, table.path) {
+            (Some(url), None) => EntryTarget::Url(url),
+            (None, Some(path)) => EntryTarget::Path(path),
+            _ => anyhow::bail!(
+                "The extra entry '{}' in config key '{}' must have either a 'url' or a 'path'",
+                table.title,
+                OPT_EXTRA_ENTRIES
+            ),
+        };
+
+        let position = match &table.position {
+            Some(position) => parse_value::<EntryPosition>(
+                &format!(
+                    "the 'position' of the extra entry '{}' in config key '{}'",
+                    table.title, OPT_EXTRA_ENTRIES
+                ),
+                position,
+                strict,
+            )?,
+            None => None,
+        };
+
+        Ok(ExtraEntry {
+            title: table.title,
+            target,
+            position: position.unwrap_or(EntryPosition::Suffix),
+            directory: table
+                .directory
+                .map(|directory| directory.trim_matches('/').to_string()),
+        })
+    }
+
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table.insert(String::from("title"), Value::from(self.title.clone()));
+        match &self.target {
+            EntryTarget::Url(url) => {
+                table.insert(String::from("url"), Value::from(url.clone()));
+            }
+            EntryTarget::Path(path) => {
+                table.insert(
+                    String::from("path"),
+                    Value::from(path.to_string_lossy().to_string()),
+                );
+            }
+        }
+        table.insert(
+            String::from("position"),
+            Value::from(self.position.as_str()),
+        );
+        if let Some(directory) = &self.directory {
+            table.insert(String::from("directory"), Value::from(directory.clone()));
+        }
+        table
+    }
+
+    /// Returns the JSON Schema of one of the `extra-entries`
+    fn item_schema() -> serde_json::Value {
+        let string = |description: &str| json!({ "type": "string", "description": description });
+
+        json!({
+            "type": "object",
+            "properties": {
+                "title": string("Title of the entry"),
+                "url": string("URL the entry links to, relative to the root of the built book if it has no scheme"),
+                "path": string("Markdown file the entry links to, relative to the source directory"),
+                "position": {
+                    "type": "string",
+                    "enum": EntryPosition::VALUES,
+                    "description": "Whether the entry is listed before or after the other entries. Default: \"suffix\"",
+                },
+                "directory": string("Directory the entry is listed in, relative to the source directory. Default: the source directory"),
+            },
+            "required": ["title"],
+            "oneOf": [{ "required": ["url"] }, { "required": ["path"] }],
+            "additionalProperties": false,
+        })
+    }
+}
+
+/// Define where an extra entry is listed in its directory
+#[derive(PartialEq, Clone)]
+pub enum EntryPosition {
+    /// Before the other entries
+    Prefix,
+    /// After the other entries (default)
+    Suffix,
+}
+
+impl EntryPosition {
+    /// The values the option accepts
+    pub const VALUES: &'static [&'static str] = &["prefix", "suffix"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntryPosition::Prefix => "prefix",
+            EntryPosition::Suffix => "suffix",
+        }
+    }
+}
+
+impl FromStr for EntryPosition {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<EntryPosition, Error> {
+        match s {
+            "prefix" => Ok(EntryPosition::Prefix),
+            "suffix" => Ok(EntryPosition::Suffix),
+            _ => anyhow::bail!(
+                "'{}' is not one of {}",
+                s,
+                quoted_values(EntryPosition::VALUES)
+            ),
+        }
+    }
+}
+
+/// Define where the entry of an extra root is listed in the source directory
+#[derive(PartialEq, Clone)]
+pub enum MountPosition {
+    /// Sort it with the other entries, by its mount name (default)
+    Sorted,
+    /// List it before the other entries
+    Start,
+    /// List it after the other entries
+    End,
+}
+
+impl MountPosition {
+    /// The values the option accepts
+    pub const VALUES: &'static [&'static str] = &["sorted", "start", "end"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MountPosition::Sorted => "sorted",
+            MountPosition::Start => "start",
+            MountPosition::End => "end",
+        }
+    }
+}
+
+impl FromStr for MountPosition {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<MountPosition, Error> {
+        match s {
+            "sorted" => Ok(MountPosition::Sorted),
+            "start" => Ok(MountPosition::Start),
+            "end" => Ok(MountPosition::End),
+            _ => anyhow::bail!(
+                "'{}' is not one of {}",
+                s,
+                quoted_values(MountPosition::VALUES)
+            ),
+        }
+    }
+}
+
+/// Define the behavior for entries deeper than the maximum depth
+#[derive(PartialEq, Clone)]
+pub enum MaxDepthBehavior {
+    /// Leave the entries out of the summary (default)
+    Omit,
+    /// List the entries at the same level as their deepest visible ancestor
+    Flatten,
+    /// Leave the entries out of the summary but build them as hidden pages,
+    /// so they are reachable from the pages that link to them
+    Hide,
+}
+
+impl MaxDepthBehavior {
+    /// The values the option accepts
+    pub const VALUES: &'static [&'static str] = &["omit", "flatten", "hide"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MaxDepthBehavior::Omit => "omit",
+            MaxDepthBehavior::Flatten => "flatten",
+            MaxDepthBehavior::Hide => "hide",
+        }
+    }
+}
+
+impl FromStr for MaxDepthBehavior {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<MaxDepthBehavior, Error> {
+        match s {
+            "omit" => Ok(MaxDepthBehavior::Omit),
+            "flatten" => Ok(MaxDepthBehavior::Flatten),
+            "hide" => Ok(MaxDepthBehavior::Hide),
+            _ => anyhow::bail!(
+                "'{}' is not one of {}",
+                s,
+                quoted_values(MaxDepthBehavior::VALUES)
+            ),
+        }
+    }
+}
+
+/// Define how the entries in a directory are sorted
+#[derive(PartialEq, Clone, Copy)]
+pub struct SortOrder {
+    pub key: SortKey,
+    /// Whether the order is reversed (for example, newest first)
+    pub reverse: bool,
+    /// Whether files and directories are listed as separate groups. The
+    /// groups are not affected by `reverse`.
+    pub group: SortGroup,
+}
+
+/// Define the value entries are sorted by
+#[derive(PartialEq, Clone, Copy)]
+pub enum SortKey {
+    /// Sort by path (default)
+    Path,
+    /// Sort by path, comparing numbers by value (`2` before `10`)
+    NaturalPath,
+    /// Sort by title
+    Title,
+    /// Sort by the file's modification time
+    ModifiedTime,
+    /// Sort by the `date` field in the file's front matter
+    Date,
+    /// Sort by the time of the first git commit of the file
+    GitFirstCommit,
+    /// Sort by the time of the last git commit of the file
+    GitLastCommit,
+}
+
+impl SortKey {
+    /// The values the option accepts
+    pub const VALUES: &'static [&'static str] = &[
+        "path",
+        "natural",
+        "title",
+        "mtime",
+        "date",
+        "git-first",
+        "git-last",
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortKey::Path => "path",
+            SortKey::NaturalPath => "natural",
+            SortKey::Title => "title",
+            SortKey::ModifiedTime => "mtime",
+            SortKey::Date => "date",
+            SortKey::GitFirstCommit => "git-first",
+            SortKey::GitLastCommit => "git-last",
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<SortKey, Error> {
+        match s {
+            "path" => Ok(SortKey::Path),
+            "natural" => Ok(SortKey::NaturalPath),
+            "title" => Ok(SortKey::Title),
+            "mtime" => Ok(SortKey::ModifiedTime),
+            "date" => Ok(SortKey::Date),
+            "git-first" => Ok(SortKey::GitFirstCommit),
+            "git-last" => Ok(SortKey::GitLastCommit),
+            _ => anyhow::bail!("'{}' is not one of {}", s, quoted_values(SortKey::VALUES)),
+        }
+    }
+}
+
+/// Define how files and directories are grouped before sorting
+#[derive(PartialEq, Clone, Copy)]
+pub enum SortGroup {
+    /// Files and directories are sorted together (default)
+    Mixed,
+    /// Directories are listed before files
+    DirectoriesFirst,
+    /// Files are listed before directories
+    FilesFirst,
+}
+
+impl SortGroup {
+    /// The values the option accepts
+    pub const VALUES: &'static [&'static str] = &["mixed", "directories-first", "files-first"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortGroup::Mixed => "mixed",
+            SortGroup::DirectoriesFirst => "directories-first",
+            SortGroup::FilesFirst => "files-first",
+        }
+    }
+}
+
+impl FromStr for SortGroup {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<SortGroup, Error> {
         match s {
-            "ignore" => Some(DirectoryWithoutIndexBehavior::Ignore),
-            "draft" => Some(DirectoryWithoutIndexBehavior::Draft),
-            "generate-stub-index" => Some(DirectoryWithoutIndexBehavior::GenerateStubIndex),
-            _ => None,
+            "mixed" => Ok(SortGroup::Mixed),
+            "directories-first" => Ok(SortGroup::DirectoriesFirst),
+            "files-first" => Ok(SortGroup::FilesFirst),
+            _ => anyhow::bail!("'{}' is not one of {}", s, quoted_values(SortGroup::VALUES)),
+        }
+    }
+}
+
+/// Define the behavior for pages of the reference language that are missing
+/// in another language
+#[derive(PartialEq, Clone)]
+pub enum MissingTranslationBehavior {
+    /// Do nothing
+    Ignore,
+    /// Print a warning listing the missing pages (default)
+    Warn,
+    /// Print a warning and add the missing pages to the language's summary
+    /// as draft chapters
+    Draft,
+}
+
+impl MissingTranslationBehavior {
+    /// The values the option accepts
+    pub const VALUES: &'static [&'static str] = &["ignore", "warn", "draft"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MissingTranslationBehavior::Ignore => "ignore",
+            MissingTranslationBehavior::Warn => "warn",
+            MissingTranslationBehavior::Draft => "draft",
+        }
+    }
+}
+
+impl FromStr for MissingTranslationBehavior {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<MissingTranslationBehavior, Error> {
+        match s {
+            "ignore" => Ok(MissingTranslationBehavior::Ignore),
+            "warn" => Ok(MissingTranslationBehavior::Warn),
+            "draft" => Ok(MissingTranslationBehavior::Draft),
+            _ => anyhow::bail!(
+                "'{}' is not one of {}",
+                s,
+                quoted_values(MissingTranslationBehavior::VALUES)
+            ),
+        }
+    }
+}
+
+/// Parses the value of an option enum, described by `name` in messages.
+/// Invalid values are errors if `strict` is true, otherwise they are ignored
+/// with a warning and `None` is returned.
+fn parse_value<T: FromStr<Err = Error>>(
+    name: &str,
+    value: &str,
+    strict: bool,
+) -> Result<Option<T>, Error> {
+    match value.parse() {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if strict => anyhow::bail!(
+            "Invalid value of {}: {}. Set '{} = false' to ignore invalid values",
+            name,
+            e,
+            OPT_STRICT
+        ),
+        Err(e) => {
+            eprintln!("Warn: Ignoring {}: {}", name, e);
+            Ok(None)
+        }
+    }
+}
+
+/// Returns the values of an option quoted and separated by commas, for error
+/// messages
+pub(crate) fn quoted_values(values: &[&str]) -> String {
+    values
+        .iter()
+        .map(|value| format!("'{}'", value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_option_is_read_back_from_its_table() {
+        let mut config = AutoGenConfig::new();
+        config.max_depth = Some(2);
+        config
+            .titles
+            .insert(String::from("guide"), String::from("Guide"));
+        config
+            .language_configs
+            .insert(String::from("ja"), Table::new());
+        config
+            .renderer_configs
+            .insert(String::from("epub"), Table::new());
+        config.export_file = Some(PathBuf::from("toc.json"));
+        config.cache_file = Some(PathBuf::from("cache.json"));
+        config.output = Some(PathBuf::from("SUMMARY.md"));
+        let extra: Table = toml::from_str(
+            "extra-roots = [{ path = \"docs\" }]\n\
+             extra-entries = [{ title = \"Home\", url = \"https://example.com\" }]",
+        )
+        .unwrap();
+        config.apply_table(&extra, Path::new("")).unwrap();
+
+        let table = config.to_table();
+        for option in OPTIONS {
+            assert!(table.contains_key(option.key), "{} is missing", option.key);
+        }
+
+        let mut read_back = AutoGenConfig::new();
+        read_back.apply_table(&table, Path::new("")).unwrap();
+        assert_eq!(read_back.to_table(), table);
+    }
+
+    #[test]
+    fn pattern_wildcards_stop_at_slashes() {
+        let matches = |pattern: &str, text: &str| {
+            let pattern: Vec<char> = pattern.chars().collect();
+            let text: Vec<char> = text.chars().collect();
+            matches_pattern(&pattern, &text)
+        };
+
+        assert!(matches("_*.md", "_header.md"));
+        assert!(matches("*", ""));
+        assert!(matches("chapter-?.md", "chapter-1.md"));
+        assert!(!matches("chapter-?.md", "chapter-10.md"));
+        assert!(matches("vendor/*/SUMMARY.md", "vendor/book/SUMMARY.md"));
+        assert!(!matches("vendor/*/SUMMARY.md", "vendor/a/b/SUMMARY.md"));
+        assert!(!matches("vendor?SUMMARY.md", "vendor/SUMMARY.md"));
+        assert!(!matches("*.md", "guide.mdx"));
+    }
+
+    #[test]
+    fn patterns_without_slash_match_the_file_name() {
+        let root = Path::new("/book/src");
+        let patterns = vec![String::from("_*.md"), String::from("/vendor/*/LICENSE.md/")];
+
+        assert!(matches_any_pattern(
+            &patterns,
+            root,
+            Path::new("/book/src/guide/_header.md")
+        ));
+        assert!(matches_any_pattern(
+            &patterns,
+            root,
+            Path::new("/book/src/vendor/book/LICENSE.md")
+        ));
+        assert!(!matches_any_pattern(
+            &patterns,
+            root,
+            Path::new("/book/src/LICENSE.md")
+        ));
+        assert!(!matches_any_pattern(
+            &[],
+            root,
+            Path::new("/book/src/_header.md")
+        ));
+    }
+
+    #[test]
+    fn reserved_files_default_to_summaries_and_partials() {
+        let config = AutoGenConfig::new();
+        let root = Path::new("/book/src");
+
+        for file in ["SUMMARY.md", "vendor/book/SUMMARY.md", "guide/_header.md"] {
+            assert!(config.is_reserved_file(root, &root.join(file)), "{}", file);
+        }
+        for file in ["CHANGELOG.md", "LICENSE.md", "guide/header.md"] {
+            assert!(!config.is_reserved_file(root, &root.join(file)), "{}", file);
+        }
+    }
+
+    // The environment is shared by all tests, so this is the only test that
+    // sets variables
+    #[test]
+    fn env_variables_are_converted_to_the_type_of_their_option() {
+        let vars = [
+            ("AUTO_GEN_SUMMARY_FIRST_LINE_AS_LINK_TEXT", "true"),
+            ("AUTO_GEN_SUMMARY_MAX_DEPTH", "2"),
+            (
+                "AUTO_GEN_SUMMARY_RESERVED_FILES",
+                "[\"SUMMARY.md\", \"drafts/*.md\"]",
+            ),
+            ("AUTO_GEN_SUMMARY_DIRECTORY_WITHOUT_INDEX_BEHAVIOR", "draft"),
+            ("AUTO_GEN_SUMMARY_OUTPUT", "generated/SUMMARY.md"),
+            ("AUTO_GEN_SUMMARY_NOT_AN_OPTION", "ignored"),
+        ];
+        for (name, value) in vars {
+            std::env::set_var(name, value);
+        }
+
+        let mut config = AutoGenConfig::new();
+        let result = config.apply_env();
+
+        std::env::set_var("AUTO_GEN_SUMMARY_MAX_DEPTH", "deep");
+        let invalid = AutoGenConfig::new().apply_env();
+
+        for (name, _) in vars {
+            std::env::remove_var(name);
+        }
+
+        result.unwrap();
+        assert!(config.first_line_as_link_text);
+        assert_eq!(config.max_depth, Some(2));
+        assert_eq!(config.reserved_files, vec!["SUMMARY.md", "drafts/*.md"]);
+        assert!(config.directory_without_index_behavior == DirectoryWithoutIndexBehavior::Draft);
+        assert_eq!(config.output, Some(PathBuf::from("generated/SUMMARY.md")));
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn directory_config_files_reject_book_level_keys() {
+        let dir = std::env::temp_dir().join(format!(
+            "auto-gen-summary-directory-config-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(DIRECTORY_CONFIG_FILE),
+            "draft-behavior = \"omit\"\nsort-reverse = true\n\
+             extra-entries = [{ title = \"Home\", url = \"https://example.com\" }]",
+        )
+        .unwrap();
+
+        let mut config = AutoGenConfig::new();
+        let strict = config.for_directory(&dir).map(|_| ());
+        config.strict = false;
+        let lenient = config.for_directory(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let error = strict.unwrap_err().to_string();
+        assert!(error.contains("'draft-behavior'"), "{}", error);
+        let lenient = lenient.unwrap().unwrap();
+        assert!(lenient.draft_behavior == DraftBehavior::Keep);
+        assert!(lenient.extra_entries.is_empty());
+        assert!(lenient.sort_order.reverse);
+    }
+
+    #[test]
+    fn unused_renderer_and_language_tables_are_checked() {
+        for table in [
+            "[renderer.epub]\ndraft-behaviour = \"omit\"",
+            "[language.ja]\nfirst-line-as-link-text = \"true\"",
+            "[language.ja.renderer.epub]\ndraft-behaviour = \"omit\"",
+        ] {
+            let table: Table = toml::from_str(table).unwrap();
+            let error = AutoGenConfig::new()
+                .apply_table(&table, Path::new(""))
+                .unwrap_err();
+            assert!(
+                format!("{:#}", error).contains("Invalid config for"),
+                "{:#}",
+                error
+            );
+        }
+
+        let table: Table =
+            toml::from_str("[renderer.epub]\nstrict = false\ndraft-behaviour = \"omit\"").unwrap();
+        AutoGenConfig::new()
+            .apply_table(&table, Path::new(""))
+            .unwrap();
+    }
+
+    #[test]
+    fn paths_in_renderer_tables_are_relative_to_the_book_root() {
+        let mut config = AutoGenConfig::new();
+        config.book_root = PathBuf::from("/book");
+        let table: Table = toml::from_str(
+            "[renderer.epub]\n\
+             output = \"epub/SUMMARY.md\"\n\
+             cache-file = \"cache.json\"\n\
+             export-file = \"toc.json\"\n\
+             extra-roots = [{ path = \"docs\" }]",
+        )
+        .unwrap();
+        config.apply_table(&table, Path::new("/book")).unwrap();
+
+        let epub = config.for_renderer("epub").unwrap().unwrap();
+        assert_eq!(epub.output, Some(PathBuf::from("/book/epub/SUMMARY.md")));
+        assert_eq!(epub.cache_file, Some(PathBuf::from("/book/cache.json")));
+        assert_eq!(epub.export_file, Some(PathBuf::from("/book/toc.json")));
+        assert_eq!(epub.extra_roots[0].path, PathBuf::from("/book/docs"));
+    }
+
+    #[test]
+    fn every_option_is_in_the_schema_and_the_readme() {
+        let schema = json_schema();
+        let readme = include_str!("../../README.md");
+
+        for option in OPTIONS {
+            assert!(
+                schema["properties"].get(option.key).is_some(),
+                "{} is missing in the schema",
+                option.key
+            );
+            assert!(
+                readme.contains(&format!("- `{}`", option.key))
+                    || readme.contains(&format!("- `{}.", option.key)),
+                "{} is missing in the README",
+                option.key
+            );
         }
     }
 }
@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Error};
+use serde_derive::Serialize;
+
+use crate::auto_gen_summary::config::{quoted_values, AutoGenConfig, MaxDepthBehavior};
+use crate::auto_gen_summary::{MdEntry, RelativizedLink};
+
+/// The table of contents of one summary, as written to an export file
+#[derive(Serialize, Clone)]
+pub struct Toc {
+    /// The language root the summary belongs to, in multilingual books
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// The title of the root index file, if the root directory has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub chapters: Vec<TocEntry>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct TocEntry {
+    pub title: String,
+    /// The path of the file relative to the source directory (or language
+    /// root). Not set for drafts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// The URL of an extra entry that links outside the book
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub draft: bool,
+    /// The nesting level in the summary, starting at 0
+    pub depth: usize,
+    pub directory: bool,
+    /// The front matter of the file (or directory index file)
+    pub front_matter: BTreeMap<String, String>,
+    pub children: Vec<TocEntry>,
+}
+
+/// The tables of contents of every language root of a multilingual book
+#[derive(Serialize)]
+struct LanguageTocs<'a> {
+    languages: &'a [Toc],
+}
+
+impl Toc {
+    /// Builds the table of contents from the sorted tree of a summary.
+    /// Entries deeper than the `max_depth` of the config are left out or
+    /// flattened like in the summary.
+    pub fn from_tree(
+        root_dir: &Path,
+        language: Option<&str>,
+        tree: Option<&MdEntry>,
+        config: &AutoGenConfig,
+    ) -> Toc {
+        let Some(tree) = tree else {
+            return Toc {
+                language: language.map(String::from),
+                title: None,
+                path: None,
+                chapters: Vec::new(),
+            };
+        };
+
+        let summary_file_name = config.summary_file_name();
+        let mut chapters = Vec::new();
+        for child in tree.children.iter().filter(|child| {
+            // the generated summary is never listed
+            child.is_dir
+                || child.path.as_ref().and_then(|path| path.file_name())
+                    != Some(summary_file_name.as_os_str())
+        }) {
+            TocEntry::push_entries(root_dir, 0, child, config, &mut chapters);
+        }
+
+        Toc {
+            language: language.map(String::from),
+            title: tree.path.as_ref().map(|_| tree.title.clone()),
+            path: tree.path.as_ref().map(|_| relative_link(root_dir, tree)),
+            chapters,
+        }
+    }
+}
+
+impl TocEntry {
+    /// Adds the entry of `entry` to `entries`. At the maximum depth, its
+    /// children are left out, or added to `entries` after it if they are
+    /// flattened.
+    fn push_entries(
+        root_dir: &Path,
+        depth: usize,
+        entry: &MdEntry,
+        config: &AutoGenConfig,
+        entries: &mut Vec<TocEntry>,
+    ) {
+        let mut toc_entry = TocEntry {
+            title: entry.title.clone(),
+            path: entry.path.as_ref().map(|_| relative_link(root_dir, entry)),
+            url: entry.url.clone(),
+            draft: entry.path.is_none() && entry.url.is_none(),
+            depth,
+            directory: entry.is_dir,
+            front_matter: entry.front_matter.clone(),
+            children: Vec::new(),
+        };
+
+        match config.max_depth {
+            Some(max_depth) if depth + 1 >= max_depth => {
+                entries.push(toc_entry);
+                if config.max_depth_behavior == MaxDepthBehavior::Flatten {
+                    for child in &entry.children {
+                        TocEntry::push_entries(root_dir, depth, child, config, entries);
+                    }
+                }
+            }
+            _ => {
+                for child in &entry.children {
+                    TocEntry::push_entries(
+                        root_dir,
+                        depth + 1,
+                        child,
+                        config,
+                        &mut toc_entry.children,
+                    );
+                }
+                entries.push(toc_entry);
+            }
+        }
+    }
+}
+
+fn relative_link(root_dir: &Path, entry: &MdEntry) -> String {
+    RelativizedLink::from(root_dir, &entry.path)
+        .0
+        .replace('\\', "/")
+}
+
+/// Define the format of an exported table of contents
+#[derive(PartialEq, Clone, Copy)]
+pub enum ExportFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl FromStr for ExportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ExportFormat, Error> {
+        match s {
+            "json" => Ok(ExportFormat::Json),
+            "yaml" | "yml" => Ok(ExportFormat::Yaml),
+            "toml" => Ok(ExportFormat::Toml),
+            _ => anyhow::bail!(
+                "'{}' is not one of {}",
+                s,
+                quoted_values(ExportFormat::VALUES)
+            ),
+        }
+    }
+}
+
+impl ExportFormat {
+    /// The values the format accepts, which are also the file extensions it
+    /// is picked from
+    pub const VALUES: &'static [&'static str] = &["json", "yaml", "yml", "toml"];
+
+    /// Returns the format matching the extension of `path`
+    pub fn from_path(path: &Path) -> Option<ExportFormat> {
+        path.extension()?
+            .to_string_lossy()
+            .to_lowercase()
+            .parse()
+            .ok()
+    }
+
+    /// Serializes the tables of contents. A book that is not split by
+    /// language has a single table of contents; the ones of a multilingual
+    /// book are listed under `languages`.
+    pub fn serialize(&self, tocs: &[Toc]) -> Result<String, Error> {
+        match tocs {
+            [toc] if toc.language.is_none() => self.serialize_value(toc),
+            _ => self.serialize_value(&LanguageTocs { languages: tocs }),
+        }
+    }
+
+    fn serialize_value(&self, value: &impl serde::Serialize) -> Result<String, Error> {
+        let result = match self {
+            ExportFormat::Json => serde_json::to_string_pretty(value).map_err(Error::from),
+            ExportFormat::Yaml => serde_yaml_ng::to_string(value).map_err(Error::from),
+            // Converted to a value first, which puts the tables after the other values
+            ExportFormat::Toml => toml::Value::try_from(value)
+                .and_then(|value| toml::to_string_pretty(&value))
+                .map_err(Error::from),
+        };
+
+        result.context("Failed to serialize the table of contents")
+    }
+}
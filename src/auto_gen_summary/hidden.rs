@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use mdbook::book::{Book, BookItem, Chapter};
+use mdbook::preprocess::PreprocessorContext;
+
+use crate::auto_gen_summary::config::AutoGenConfig;
+use crate::auto_gen_summary::{relative_path, roots, write_file_atomically, MdEntry};
+
+/// The front matter key that hides a page when set to `true`
+const HIDDEN_FRONT_MATTER_KEY: &str = "hidden";
+
+/// The renderer that hidden pages are built for. Other renderers have no way
+/// to build a page without listing it, so hidden pages are left out.
+const HIDDEN_PAGES_RENDERER: &str = "html";
+
+/// The stylesheet, relative to the book root, that hides the links to the
+/// hidden pages
+pub const HIDDEN_PAGES_STYLESHEET: &str = "auto-gen-summary-hidden.css";
+
+/// A page that is built but not listed in the summary
+#[derive(Clone)]
+pub struct HiddenPage {
+    pub title: String,
+    /// The path of the file, relative to the source directory. Starts with
+    /// `..` for the files of an extra root.
+    pub path: PathBuf,
+}
+
+/// Returns whether the file at `path` is hidden, by its front matter or by
+/// the `hidden-files` option.
+pub fn is_hidden(
+    root_dir: &Path,
+    path: &Path,
+    front_matter: &BTreeMap<String, String>,
+    config: &AutoGenConfig,
+) -> bool {
+    front_matter
+        .get(HIDDEN_FRONT_MATTER_KEY)
+        .map(String::as_str)
+        == Some("true")
+        || config.is_hidden_file(root_dir, path)
+}
+
+/// Removes the hidden entries and their children from the tree, and returns
+/// the pages they link to, sorted by path.
+pub fn take_hidden_pages(root_dir: &Path, entry: &mut MdEntry) -> Vec<HiddenPage> {
+    let mut pages = Vec::new();
+    take_hidden_pages_recursive(root_dir, entry, &mut pages);
+    pages.sort_by(|a, b| a.path.cmp(&b.path));
+    pages
+}
+
+fn take_hidden_pages_recursive(root_dir: &Path, entry: &mut MdEntry, pages: &mut Vec<HiddenPage>) {
+    let mut children = Vec::with_capacity(entry.children.len());
+
+    for mut child in entry.children.drain(..) {
+        if child.hidden {
+            collect_pages(root_dir, &child, pages);
+        } else {
+            take_hidden_pages_recursive(root_dir, &mut child, pages);
+            children.push(child);
+        }
+    }
+
+    entry.children = children;
+}
+
+/// Adds the page of `entry` and of all its children to `pages`. Drafts have
+/// no page and are dropped.
+pub fn collect_pages(root_dir: &Path, entry: &MdEntry, pages: &mut Vec<HiddenPage>) {
+    if let Some(path) = &entry.path {
+        pages.push(HiddenPage {
+            title: entry.title.clone(),
+            path: relative_path(root_dir, path),
+        });
+    }
+
+    for child in &entry.children {
+        collect_pages(root_dir, child, pages);
+    }
+}
+
+/// Adds the hidden pages to the book built for the html renderer, so they
+/// are reachable by URL.
+///
+/// The html renderer lists every chapter of the book in its sidebar, so the
+/// links to the hidden pages are hidden by a generated stylesheet, which is
+/// written to the book root if `output.html.additional-css` lists it.
+pub fn add_hidden_pages(
+    book: &mut Book,
+    source_dir: &Path,
+    pages: &[HiddenPage],
+    ctx: &PreprocessorContext,
+    config: &AutoGenConfig,
+) -> Result<(), Error> {
+    if ctx.renderer != HIDDEN_PAGES_RENDERER {
+        return Ok(());
+    }
+
+    let stylesheet_listed = ctx
+        .config
+        .html_config()
+        .map(|html_config| {
+            html_config
+                .additional_css
+                .iter()
+                .any(|css| css == Path::new(HIDDEN_PAGES_STYLESHEET))
+        })
+        .unwrap_or(false);
+    if !pages.is_empty() && !stylesheet_listed {
+        eprintln!(
+            "Warn: The hidden pages are listed in the sidebar. Add '{}' to 'output.html.additional-css' to hide them",
+            HIDDEN_PAGES_STYLESHEET
+        );
+    }
+
+    // The paths the pages are built at
+    let page_paths: Vec<PathBuf> = pages
+        .iter()
+        .map(|page| roots::mounted_path(source_dir, config, &page.path))
+        .collect();
+
+    for (page, page_path) in pages.iter().zip(&page_paths) {
+        let file_path = source_dir.join(&page.path);
+        let content = fs::read_to_string(&file_path)
+            .with_context(|| format!("Failed to read '{}'", file_path.display()))?;
+        let mut chapter = Chapter::new(&page.title, content, page_path, Vec::new());
+        chapter.source_path = Some(page.path.clone());
+        book.push_item(BookItem::Chapter(chapter));
+    }
+
+    if stylesheet_listed {
+        // mdbook fails if the stylesheet is missing, so it is written even
+        // if no page is hidden
+        write_stylesheet(
+            &ctx.root.join(HIDDEN_PAGES_STYLESHEET),
+            &stylesheet(book, &page_paths),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Returns the stylesheet that hides the links to the pages at `page_paths`
+/// in the sidebar and the "next" buttons.
+///
+/// The links are relative to the root of the book, prefixed with the path
+/// to the root of the page they are on, so the rules match every prefix up
+/// to the deepest chapter of the book.
+fn stylesheet(book: &Book, page_paths: &[PathBuf]) -> String {
+    let mut content = String::from(
+        "/* Generated by mdbook-auto-gen-summary. Hides the links to hidden pages. */\n",
+    );
+    if page_paths.is_empty() {
+        return content;
+    }
+
+    let max_depth = book
+        .iter()
+        .filter_map(|item| match item {
+            BookItem::Chapter(chapter) => chapter.path.as_ref(),
+            _ => None,
+        })
+        .map(|path| path.components().count().saturating_sub(1))
+        .max()
+        .unwrap_or_default();
+
+    let mut selectors = Vec::new();
+    for depth in 0..=max_depth {
+        let root = "../".repeat(depth);
+        for page_path in page_paths {
+            let href = format!(
+                "{}{}",
+                root,
+                page_path
+                    .with_extension("html")
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            );
+            selectors.push(format!(
+                ".sidebar li.chapter-item:has(> a[href=\"{}\"])",
+                href
+            ));
+            selectors.push(format!("a[rel~=\"next\"][href=\"{}\"]", href));
+        }
+    }
+
+    content.push_str(&format!(
+        "{} {{\n    display: none;\n}}\n",
+        selectors.join(",\n")
+    ));
+    content
+}
+
+/// Writes the stylesheet if its content changed, so `mdbook serve` doesn't
+/// see a change on every build
+fn write_stylesheet(path: &Path, content: &str) -> Result<(), Error> {
+    if fs::read_to_string(path).ok().as_deref() == Some(content) {
+        return Ok(());
+    }
+
+    write_file_atomically(path, content)
+}
@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+
+use crate::auto_gen_summary::config::{AutoGenConfig, MissingTranslationBehavior};
+use crate::auto_gen_summary::export::Toc;
+use crate::auto_gen_summary::metadata::MetadataCache;
+use crate::auto_gen_summary::verify::verify_summary;
+use crate::auto_gen_summary::{hidden, links};
+use crate::auto_gen_summary::{
+    prepare_entry_tree, relative_key, render_summary, walk_dir, GeneratedSummary, MdEntry,
+};
+
+/// The tree of entries of one language root
+struct LanguageRoot<'a> {
+    language: &'a str,
+    dir: PathBuf,
+    config: AutoGenConfig,
+    tree: Option<MdEntry>,
+}
+
+/// Generates the summary of every language root set by the `languages`
+/// option.
+///
+/// Pages of the reference (first) language that are missing in another
+/// language are reported, and added to that language's summary as drafts if
+/// configured.
+pub fn render_language_summaries(
+    source_dir: &Path,
+    config: &AutoGenConfig,
+    cache: &MetadataCache,
+) -> Result<Vec<GeneratedSummary>, Error> {
+    let mut roots = Vec::new();
+
+    for language in &config.languages {
+        let dir = source_dir.join(language);
+        if !dir.is_dir() {
+            anyhow::bail!(
+                "The directory '{}' for language '{}' does not exist",
+                dir.display(),
+                language
+            );
+        }
+
+        let config = config.for_language(language)?;
+        let tree = walk_dir(&dir, &dir, &config, cache, &mut Vec::new())?;
+
+        roots.push(LanguageRoot {
+            language,
+            dir,
+            config,
+            tree,
+        });
+    }
+
+    if config.missing_translation_behavior != MissingTranslationBehavior::Ignore {
+        let draft = config.missing_translation_behavior == MissingTranslationBehavior::Draft;
+        let (reference, others) = roots.split_first_mut().unwrap();
+
+        for other in others {
+            let mut missing = Vec::new();
+
+            match (&reference.tree, &mut other.tree) {
+                (Some(reference_tree), Some(tree)) => align_entries(
+                    &reference.dir,
+                    reference_tree,
+                    &other.dir,
+                    tree,
+                    draft,
+                    &mut missing,
+                ),
+                (Some(_), None) => missing.push(String::from("/")),
+                (None, _) => {}
+            }
+
+            if !missing.is_empty() {
+                missing.sort();
+                eprintln!(
+                    "Warn: The following pages of language '{}' are missing in language '{}':\n  - {}",
+                    reference.language,
+                    other.language,
+                    missing.join("\n  - ")
+                );
+            }
+        }
+    }
+
+    let mut summaries = Vec::new();
+
+    for root in roots {
+        let mut tree = root.tree;
+        let mut hidden_pages = Vec::new();
+        if let Some(tree) = &mut tree {
+            hidden_pages = hidden::take_hidden_pages(&root.dir, tree);
+            hidden_pages.append(&mut prepare_entry_tree(&root.dir, tree, &root.config));
+        }
+
+        let links = tree.as_ref().map(links::collect_links).unwrap_or_default();
+        let toc = Toc::from_tree(&root.dir, Some(root.language), tree.as_ref(), &root.config);
+
+        let (content, chapters) = render_summary(&root.dir, tree, &root.config);
+        let language = root.language;
+        verify_summary(&content, &chapters)
+            .with_context(|| format!("Invalid summary for language '{}'", language))?;
+
+        summaries.push(GeneratedSummary {
+            path: root.config.summary_path(&root.dir),
+            content,
+            toc,
+            hidden_pages,
+            links,
+        });
+    }
+
+    Ok(summaries)
+}
+
+/// Pages of one language root that are missing in another
+pub struct MissingPages {
+    pub language_dir: PathBuf,
+    pub missing_in: PathBuf,
+    /// Paths relative to the language roots. Directories end with `/`.
+    pub pages: Vec<String>,
+}
+
+/// Compares the trees of two or more language root directories. For every
+/// pair of roots, lists the pages of one root that are missing in the other.
+///
+/// Only pairs with missing pages are returned.
+pub fn compare_language_roots(
+    language_dirs: &[PathBuf],
+    config: &AutoGenConfig,
+) -> Result<Vec<MissingPages>, Error> {
+    let mut trees = Vec::new();
+    let cache = MetadataCache::default();
+
+    for dir in language_dirs {
+        if !dir.is_dir() {
+            anyhow::bail!("The directory '{}' does not exist", dir.display());
+        }
+
+        let language = dir.file_name().unwrap_or_default().to_string_lossy();
+        let config = config.for_language(&language)?;
+        trees.push(walk_dir(dir, dir, &config, &cache, &mut Vec::new())?);
+    }
+
+    let mut result = Vec::new();
+
+    for i in 0..trees.len() {
+        for j in 0..trees.len() {
+            if i == j {
+                continue;
+            }
+
+            let mut pages = Vec::new();
+            // Taken out of the list to borrow it mutably next to the reference
+            let mut tree = trees[j].take();
+
+            match (&trees[i], &mut tree) {
+                (Some(reference), Some(tree)) => align_entries(
+                    &language_dirs[i],
+                    reference,
+                    &language_dirs[j],
+                    tree,
+                    false,
+                    &mut pages,
+                ),
+                (Some(_), None) => pages.push(String::from("/")),
+                (None, _) => {}
+            }
+
+            trees[j] = tree;
+
+            if !pages.is_empty() {
+                pages.sort();
+                result.push(MissingPages {
+                    language_dir: language_dirs[i].clone(),
+                    missing_in: language_dirs[j].clone(),
+                    pages,
+                });
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Finds the children of `reference` that have no counterpart (an entry with
+/// the same relative path) in `entry`, and adds them to `missing`. Missing
+/// entries are added to `entry` as drafts if `draft` is true.
+fn align_entries(
+    reference_root: &Path,
+    reference: &MdEntry,
+    root: &Path,
+    entry: &mut MdEntry,
+    draft: bool,
+    missing: &mut Vec<String>,
+) {
+    for reference_child in &reference.children {
+        let relative_path = reference_child
+            .sorting_path
+            .strip_prefix(reference_root)
+            .unwrap();
+        let sorting_path = root.join(relative_path);
+
+        match entry
+            .children
+            .iter_mut()
+            .find(|child| child.sorting_path == sorting_path)
+        {
+            Some(child) => {
+                if reference_child.is_dir && child.is_dir {
+                    align_entries(reference_root, reference_child, root, child, draft, missing);
+                }
+            }
+            None => {
+                let mut key = relative_key(reference_root, &reference_child.sorting_path);
+                if reference_child.is_dir {
+                    key.push('/');
+                }
+                missing.push(key);
+
+                if draft {
+                    entry
+                        .children
+                        .push(draft_copy(reference_root, reference_child, root));
+                }
+            }
+        }
+    }
+}
+
+/// Copies a reference entry and its children as draft entries of `root`
+fn draft_copy(reference_root: &Path, reference: &MdEntry, root: &Path) -> MdEntry {
+    MdEntry {
+        title: reference.title.clone(),
+        path: None,
+        url: None,
+        sorting_path: root.join(reference.sorting_path.strip_prefix(reference_root).unwrap()),
+        is_dir: reference.is_dir,
+        front_matter: BTreeMap::new(),
+        sort_order: reference.sort_order,
+        order: reference.order.clone(),
+        hidden: reference.hidden,
+        children: reference
+            .children
+            .iter()
+            .map(|child| draft_copy(reference_root, child, root))
+            .collect(),
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use mdbook::book::{Book, BookItem, Chapter};
+use mdbook::utils::fs::path_to_root;
+
+use crate::auto_gen_summary::config::{AutoGenConfig, EntryPosition, EntryTarget, ExtraEntry};
+use crate::auto_gen_summary::metadata::MetadataCache;
+use crate::auto_gen_summary::{file_entry, MdEntry, PREPROCESSOR_NAME};
+
+/// The renderer that links to URLs are built for. Other renderers can't
+/// link a chapter to a URL, so the links are left out.
+const LINKS_RENDERER: &str = "html";
+
+/// An extra entry that links to a URL, which is written as a draft in the
+/// summary
+#[derive(Clone)]
+pub struct SummaryLink {
+    pub title: String,
+    pub url: String,
+    /// The titles of the entries it is nested in, like the `parent_names`
+    /// of a chapter
+    pub parent_names: Vec<String>,
+}
+
+/// Adds the `extra_entries` of the config to the sorted tree of
+/// `source_dir`.
+pub fn add_extra_entries(source_dir: &Path, tree: &mut MdEntry, config: &AutoGenConfig) {
+    for extra_entry in &config.extra_entries {
+        if let EntryTarget::Path(path) = &extra_entry.target {
+            if contains_path(tree, &source_dir.join(path)) {
+                eprintln!(
+                    "Warn: The extra entry '{}' is left out of the summary because '{}' is already in it",
+                    extra_entry.title,
+                    path.display()
+                );
+                continue;
+            }
+        }
+
+        let parent = match &extra_entry.directory {
+            Some(directory) => find_directory(tree, &source_dir.join(directory)),
+            None => Some(&mut *tree),
+        };
+        let Some(parent) = parent else {
+            eprintln!(
+                "Warn: The extra entry '{}' is left out of the summary because the directory '{}' is not in it",
+                extra_entry.title,
+                extra_entry.directory.as_deref().unwrap_or_default()
+            );
+            continue;
+        };
+
+        let Some(entry) = extra_entry_tree(source_dir, extra_entry, config) else {
+            continue;
+        };
+
+        match extra_entry.position {
+            EntryPosition::Prefix => parent.children.insert(0, entry),
+            EntryPosition::Suffix => parent.children.push(entry),
+        }
+    }
+}
+
+fn extra_entry_tree(
+    source_dir: &Path,
+    extra_entry: &ExtraEntry,
+    config: &AutoGenConfig,
+) -> Option<MdEntry> {
+    let mut entry = match &extra_entry.target {
+        EntryTarget::Url(url) => MdEntry {
+            title: String::new(),
+            path: None,
+            url: Some(url.clone()),
+            sorting_path: source_dir.join(&extra_entry.title),
+            is_dir: false,
+            front_matter: BTreeMap::new(),
+            sort_order: config.sort_order,
+            order: Vec::new(),
+            hidden: false,
+            children: Vec::new(),
+        },
+        EntryTarget::Path(path) => {
+            let file_path = source_dir.join(path);
+            let outside = path.is_absolute()
+                || path
+                    .components()
+                    .any(|component| component == Component::ParentDir);
+            if outside || !file_path.is_file() {
+                eprintln!(
+                    "Warn: The extra entry '{}' is left out of the summary because '{}' is not a file in the source directory",
+                    extra_entry.title,
+                    path.display()
+                );
+                return None;
+            }
+
+            let file_name = path.file_name()?.to_string_lossy().to_string();
+            match file_entry(
+                source_dir,
+                file_path,
+                file_name,
+                config,
+                &MetadataCache::default(),
+                &mut Vec::new(),
+            ) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    eprintln!(
+                        "Warn: The extra entry '{}' is left out of the summary: {:#}",
+                        extra_entry.title, e
+                    );
+                    return None;
+                }
+            }
+        }
+    };
+
+    entry.title = extra_entry.title.clone();
+    Some(entry)
+}
+
+/// Returns the directory entry of `dir` in the tree
+fn find_directory<'a>(entry: &'a mut MdEntry, dir: &Path) -> Option<&'a mut MdEntry> {
+    if entry.is_dir && entry.sorting_path == dir {
+        return Some(entry);
+    }
+
+    entry
+        .children
+        .iter_mut()
+        .find_map(|child| find_directory(child, dir))
+}
+
+/// Returns whether the tree has an entry for the file at `path`
+fn contains_path(entry: &MdEntry, path: &Path) -> bool {
+    entry.path.as_deref() == Some(path)
+        || entry
+            .children
+            .iter()
+            .any(|child| contains_path(child, path))
+}
+
+/// Returns the entries of the tree that link to a URL
+pub fn collect_links(tree: &MdEntry) -> Vec<SummaryLink> {
+    let mut links = Vec::new();
+    for child in &tree.children {
+        collect_links_recursive(child, &mut Vec::new(), &mut links);
+    }
+    links
+}
+
+fn collect_links_recursive(
+    entry: &MdEntry,
+    parent_names: &mut Vec<String>,
+    links: &mut Vec<SummaryLink>,
+) {
+    if let Some(url) = &entry.url {
+        links.push(SummaryLink {
+            title: entry.title.clone(),
+            url: url.clone(),
+            parent_names: parent_names.clone(),
+        });
+    }
+
+    parent_names.push(entry.title.clone());
+    for child in &entry.children {
+        collect_links_recursive(child, parent_names, links);
+    }
+    parent_names.pop();
+}
+
+/// Turns the drafts of the links into chapters of the book built for
+/// `renderer`.
+///
+/// The html renderer gets a page for each link that redirects to its URL,
+/// so the sidebar links to it. Other renderers have no way to link a
+/// chapter to a URL, so the drafts are removed.
+pub fn add_links(book: &mut Book, links: &[SummaryLink], renderer: &str) {
+    if links.is_empty() {
+        return;
+    }
+
+    if renderer != LINKS_RENDERER {
+        remove_link_drafts(&mut book.sections, links, &mut 0);
+        return;
+    }
+
+    for_each_link_draft(&mut book.sections, links, &mut 0, &mut |chapter, index| {
+        let page_path = PathBuf::from(PREPROCESSOR_NAME).join(format!("link-{}.md", index + 1));
+        chapter.content = redirect_page(&links[index], &page_path);
+        chapter.path = Some(page_path);
+    });
+}
+
+/// Returns whether `chapter` is the draft of `link`
+fn is_link_draft(chapter: &Chapter, link: Option<&SummaryLink>) -> bool {
+    let Some(link) = link else {
+        return false;
+    };
+
+    chapter.path.is_none()
+        && chapter.sub_items.is_empty()
+        && chapter.name == link.title
+        && chapter.parent_names == link.parent_names
+}
+
+/// Calls `f` with the draft of each link and the index of the link.
+///
+/// The drafts are in the same order as the links, so each draft is matched
+/// to the next link, which tells apart links with the same title.
+fn for_each_link_draft(
+    items: &mut [BookItem],
+    links: &[SummaryLink],
+    next: &mut usize,
+    f: &mut dyn FnMut(&mut Chapter, usize),
+) {
+    for item in items {
+        let BookItem::Chapter(chapter) = item else {
+            continue;
+        };
+        if is_link_draft(chapter, links.get(*next)) {
+            f(chapter, *next);
+            *next += 1;
+        } else {
+            for_each_link_draft(&mut chapter.sub_items, links, next, f);
+        }
+    }
+}
+
+/// Returns the content of a page that redirects to the URL of `link`.
+///
+/// The redirect only runs on the page itself, since the content of every
+/// page is also part of the print page.
+fn redirect_page(link: &SummaryLink, page_path: &Path) -> String {
+    let url = if link.url.contains(':') || link.url.starts_with('/') {
+        link.url.clone()
+    } else {
+        format!("{}{}", path_to_root(page_path), link.url)
+    };
+    let page_name = page_path
+        .with_extension("")
+        .to_string_lossy()
+        .replace('\\', "/");
+
+    format!(
+        "# {title}\n\n<script>\nif (window.location.pathname.replace(/\\.html$/, \"\").endsWith(\"/{page}\")) {{\n    window.location.replace({url_json});\n}}\n</script>\n\n[{url_text}](<{url_destination}>)\n",
+        title = escape_markdown(&link.title),
+        page = page_name,
+        url_json = serde_json::to_string(&url).unwrap_or_default(),
+        url_text = escape_markdown(&url),
+        url_destination = url.replace('\\', "\\\\").replace('<', "\\<").replace('>', "\\>"),
+    )
+}
+
+/// Escapes the ASCII punctuation of `text`, so it is rendered as written
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii_punctuation() {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Removes the draft of each link, matched like in `for_each_link_draft`
+fn remove_link_drafts(items: &mut Vec<BookItem>, links: &[SummaryLink], next: &mut usize) {
+    let mut i = 0;
+    while i < items.len() {
+        if let BookItem::Chapter(chapter) = &mut items[i] {
+            if is_link_draft(chapter, links.get(*next)) {
+                items.remove(i);
+                *next += 1;
+                continue;
+            }
+            remove_link_drafts(&mut chapter.sub_items, links, next);
+        }
+        i += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(title: &str, url: &str) -> SummaryLink {
+        SummaryLink {
+            title: String::from(title),
+            url: String::from(url),
+            parent_names: Vec::new(),
+        }
+    }
+
+    fn book_with_drafts(titles: &[&str]) -> Book {
+        let mut book = Book::new();
+        for title in titles {
+            book.push_item(BookItem::Chapter(Chapter::new_draft(title, Vec::new())));
+        }
+        book
+    }
+
+    fn chapters(book: &Book) -> Vec<&Chapter> {
+        book.sections
+            .iter()
+            .filter_map(|item| match item {
+                BookItem::Chapter(chapter) => Some(chapter),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn links_with_the_same_title_get_their_own_page() {
+        let links = [
+            link("Docs", "https://example.com/first"),
+            link("Docs", "https://example.com/second"),
+        ];
+        let mut book = book_with_drafts(&["Docs", "Docs", "Docs"]);
+
+        add_links(&mut book, &links, "html");
+
+        let chapters = chapters(&book);
+        assert_eq!(
+            chapters[0].path,
+            Some(PathBuf::from("auto-gen-summary/link-1.md"))
+        );
+        assert!(chapters[0]
+            .content
+            .contains("\"https://example.com/first\""));
+        assert_eq!(
+            chapters[1].path,
+            Some(PathBuf::from("auto-gen-summary/link-2.md"))
+        );
+        assert!(chapters[1]
+            .content
+            .contains("\"https://example.com/second\""));
+        // A draft chapter with the same title that is not a link
+        assert_eq!(chapters[2].path, None);
+    }
+
+    #[test]
+    fn other_renderers_only_remove_the_drafts_of_links() {
+        let links = [
+            link("Docs", "https://example.com/first"),
+            link("Docs", "https://example.com/second"),
+        ];
+        let mut book = book_with_drafts(&["Docs", "Docs", "Docs"]);
+
+        add_links(&mut book, &links, "markdown");
+
+        assert_eq!(chapters(&book).len(), 1);
+    }
+
+    #[test]
+    fn redirect_pages_escape_the_title_and_the_url() {
+        let page_path = PathBuf::from("auto-gen-summary/link-1.md");
+
+        let page = redirect_page(
+            &link("*API* [docs]", "https://example.com/a (b)"),
+            &page_path,
+        );
+        assert!(page.starts_with("# \\*API\\* \\[docs\\]\n"), "{}", page);
+        assert!(
+            page.ends_with(
+                "[https\\:\\/\\/example\\.com\\/a \\(b\\)](<https://example.com/a (b)>)\n"
+            ),
+            "{}",
+            page
+        );
+
+        let page = redirect_page(&link("Manual", "../manual/<index>.html"), &page_path);
+        assert!(
+            page.ends_with("(<../../manual/\\<index\\>.html>)\n"),
+            "{}",
+            page
+        );
+    }
+}
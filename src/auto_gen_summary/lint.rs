@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use serde_derive::Serialize;
+
+use crate::auto_gen_summary::config::{
+    AutoGenConfig, MaxDepthBehavior, OPT_EXCLUDE, OPT_RESERVED_FILES,
+};
+use crate::auto_gen_summary::metadata::MetadataCache;
+use crate::auto_gen_summary::{hidden, prepare_entry_tree, relative_key, roots, walk_dir, MdEntry};
+
+/// A problem with the structure of the book
+#[derive(Serialize)]
+pub struct Problem {
+    pub kind: ProblemKind,
+    /// The file or directory with the problem
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn new(kind: ProblemKind, path: PathBuf, message: impl Into<String>) -> Problem {
+        Problem {
+            kind,
+            path,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}: {}",
+            self.kind.as_str(),
+            self.path.display(),
+            self.message
+        )
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProblemKind {
+    /// A directory left out because it has no index file
+    IgnoredDirectory,
+    /// A file without an h1 heading while headings are used as titles
+    MissingHeading,
+    /// Two entries in the same directory with the same title
+    DuplicateTitle,
+    /// An index file ignored because the directory has another index file
+    ShadowedIndexFile,
+    /// An empty markdown file
+    EmptyFile,
+    /// A file or directory whose name is not valid UTF-8
+    NonUtf8Name,
+    /// A markdown file that is not listed in the summary
+    OrphanedFile,
+    /// A markdown file left out of the summary by the `exclude`,
+    /// `reserved-files` or `hidden-files` options
+    FilteredFile,
+}
+
+impl ProblemKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProblemKind::IgnoredDirectory => "ignored-directory",
+            ProblemKind::MissingHeading => "missing-heading",
+            ProblemKind::DuplicateTitle => "duplicate-title",
+            ProblemKind::ShadowedIndexFile => "shadowed-index-file",
+            ProblemKind::EmptyFile => "empty-file",
+            ProblemKind::NonUtf8Name => "non-utf8-name",
+            ProblemKind::OrphanedFile => "orphaned-file",
+            ProblemKind::FilteredFile => "filtered-file",
+        }
+    }
+}
+
+/// Walks the book in `source_dir` (or every language root) and returns the
+/// problems found. Paths in the problems are relative to `source_dir`.
+///
+/// Nothing is written to the book.
+pub fn lint(source_dir: &Path, config: &AutoGenConfig) -> Result<Vec<Problem>, Error> {
+    let mut config = config.clone();
+    config.read_only = true;
+
+    let mut roots = Vec::new();
+    if config.languages.is_empty() {
+        roots.push((PathBuf::from(source_dir), config));
+    } else {
+        for language in &config.languages {
+            roots.push((source_dir.join(language), config.for_language(language)?));
+        }
+    }
+
+    let mut problems = Vec::new();
+    let cache = MetadataCache::default();
+
+    for (root, config) in roots {
+        let Some(mut tree) = walk_dir(&root, &root, &config, &cache, &mut problems)? else {
+            continue;
+        };
+        if config.languages.is_empty() {
+            roots::add_extra_roots(&root, &mut tree, &config, &cache, &mut problems)?;
+        }
+
+        for page in hidden::take_hidden_pages(&root, &mut tree) {
+            problems.push(Problem::new(
+                ProblemKind::FilteredFile,
+                root.join(page.path),
+                "File is hidden, so it is only reachable by URL",
+            ));
+        }
+
+        prepare_entry_tree(&root, &mut tree, &config);
+        check_duplicate_titles(&tree, &mut problems);
+
+        if let Some(max_depth) = config.max_depth {
+            if config.max_depth_behavior == MaxDepthBehavior::Omit {
+                for child in &tree.children {
+                    check_depth(child, 0, max_depth, &mut problems);
+                }
+            }
+        }
+    }
+
+    for problem in &mut problems {
+        problem.path = PathBuf::from(relative_key(source_dir, &problem.path));
+    }
+
+    Ok(problems)
+}
+
+/// Checks a markdown file for problems that don't depend on the config
+pub fn check_file(path: &Path, problems: &mut Vec<Problem>) {
+    if fs::metadata(path).map(|m| m.len() == 0).unwrap_or(false) {
+        problems.push(Problem::new(
+            ProblemKind::EmptyFile,
+            PathBuf::from(path),
+            "File is empty",
+        ));
+    }
+}
+
+/// Reports every markdown file in `dir` and its subdirectories as orphaned,
+/// or as filtered if it is a reserved or excluded file
+pub fn report_orphaned_files(
+    root_dir: &Path,
+    dir: &Path,
+    config: &AutoGenConfig,
+    problems: &mut Vec<Problem>,
+) {
+    for path in sorted_dir_entries(dir) {
+        if config.is_excluded(root_dir, &path) {
+            report_filtered_files(&path, OPT_EXCLUDE, problems);
+        } else if path.is_dir() {
+            report_orphaned_files(root_dir, &path, config, problems);
+        } else if config.is_reserved_file(root_dir, &path) {
+            if !is_summary_file(root_dir, &path, config) {
+                report_filtered_files(&path, OPT_RESERVED_FILES, problems);
+            }
+        } else if is_markdown_file(&path) {
+            problems.push(Problem::new(
+                ProblemKind::OrphanedFile,
+                path,
+                "File is not listed in the summary",
+            ));
+        }
+    }
+}
+
+/// Reports the markdown file at `path`, or every markdown file in it if it
+/// is a directory, as left out of the summary by the config key `option`
+pub fn report_filtered_files(path: &Path, option: &str, problems: &mut Vec<Problem>) {
+    if path.is_dir() {
+        for path in sorted_dir_entries(path) {
+            report_filtered_files(&path, option, problems);
+        }
+    } else if is_markdown_file(path) {
+        problems.push(Problem::new(
+            ProblemKind::FilteredFile,
+            PathBuf::from(path),
+            format!(
+                "File matches '{}', so it is not listed in the summary",
+                option
+            ),
+        ));
+    }
+}
+
+/// Returns whether `path` is the generated summary, which is reserved by
+/// default but is not a problem
+pub fn is_summary_file(root_dir: &Path, path: &Path, config: &AutoGenConfig) -> bool {
+    path.parent() == Some(root_dir) && path.file_name() == Some(&config.summary_file_name())
+}
+
+fn sorted_dir_entries(dir: &Path) -> Vec<PathBuf> {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<PathBuf> = read_dir.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    paths.sort();
+    paths
+}
+
+fn is_markdown_file(path: &Path) -> bool {
+    path.extension() == Some(&OsString::from("md"))
+}
+
+fn entry_path(entry: &MdEntry) -> PathBuf {
+    entry
+        .path
+        .clone()
+        .unwrap_or_else(|| entry.sorting_path.clone())
+}
+
+fn check_duplicate_titles(entry: &MdEntry, problems: &mut Vec<Problem>) {
+    let mut first_with_title: HashMap<&str, &MdEntry> = HashMap::new();
+
+    for child in &entry.children {
+        match first_with_title.get(child.title.as_str()) {
+            Some(first) => problems.push(Problem::new(
+                ProblemKind::DuplicateTitle,
+                entry_path(child),
+                format!(
+                    "Title '{}' is also used by '{}'",
+                    child.title,
+                    entry_path(first)
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                ),
+            )),
+            None => {
+                first_with_title.insert(&child.title, child);
+            }
+        }
+
+        check_duplicate_titles(child, problems);
+    }
+}
+
+/// Reports entries left out of the summary for being deeper than `max_depth`
+fn check_depth(entry: &MdEntry, depth: usize, max_depth: usize, problems: &mut Vec<Problem>) {
+    if depth >= max_depth && entry.path.is_some() {
+        problems.push(Problem::new(
+            ProblemKind::OrphanedFile,
+            entry_path(entry),
+            "File is deeper than 'max-depth', so it is not listed in the summary",
+        ));
+    }
+
+    for child in &entry.children {
+        check_depth(child, depth + 1, max_depth, problems);
+    }
+}
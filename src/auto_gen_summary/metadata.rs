@@ -0,0 +1,177 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Error};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::auto_gen_summary::write_file_atomically;
+
+/// Version of the cache file format. Cache files of other versions are
+/// ignored.
+const CACHE_VERSION: u32 = 1;
+
+/// Information read from the content of a markdown file
+#[derive(PartialEq, Clone, Serialize, Deserialize)]
+pub struct MdMetadata {
+    /// The text of the first h1 heading. Empty if the file has none.
+    pub title: String,
+    /// The `key: value` pairs of the front matter block (delimited by `---`
+    /// lines) at the start of the file, if any.
+    pub front_matter: BTreeMap<String, String>,
+}
+
+/// Reads the metadata of a markdown file. Reading stops at the first h1
+/// heading.
+fn read_metadata(md_file_path: &Path) -> Result<MdMetadata, Error> {
+    let md_file = fs::File::open(md_file_path)
+        .with_context(|| format!("Failed to open '{}'", md_file_path.display()))?;
+    let mut md_file_reader = BufReader::new(md_file);
+    let mut buf = Vec::new();
+
+    let mut next_line = || -> Result<Option<String>, Error> {
+        buf.clear();
+        let read = md_file_reader
+            .read_until(b'\n', &mut buf)
+            .with_context(|| format!("Failed to read '{}'", md_file_path.display()))?;
+        Ok(match read {
+            0 => None,
+            _ => Some(
+                String::from_utf8_lossy(&buf)
+                    .trim_end_matches(['\n', '\r'])
+                    .to_string(),
+            ),
+        })
+    };
+
+    let mut front_matter = BTreeMap::new();
+    let mut line = next_line()?;
+
+    if line.as_deref() == Some("---") {
+        while let Some(front_matter_line) = next_line()? {
+            if front_matter_line == "---" || front_matter_line == "..." {
+                break;
+            }
+            if let Some((key, value)) = front_matter_line.split_once(':') {
+                let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+                front_matter.insert(key.trim().to_string(), value.to_string());
+            }
+        }
+        line = next_line()?;
+    }
+
+    let mut title = String::new();
+    while let Some(current_line) = line {
+        if current_line.starts_with("# ") {
+            title = current_line.trim_matches('#').trim().to_string();
+            break;
+        }
+        line = next_line()?;
+    }
+
+    Ok(MdMetadata {
+        title,
+        front_matter,
+    })
+}
+
+/// A cached metadata of a file, valid while the file's modification time
+/// and size don't change
+#[derive(PartialEq, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    modified: (u64, u32),
+    size: u64,
+    metadata: MdMetadata,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    entries: HashMap<PathBuf, CacheEntry>,
+}
+
+/// Metadata of markdown files, kept in a cache file between runs so that
+/// only files that changed are read again.
+///
+/// Safe to share between threads.
+#[derive(Default)]
+pub struct MetadataCache {
+    /// None if the cache is only kept in memory
+    file: Option<PathBuf>,
+    /// The entries read from the cache file
+    loaded: HashMap<PathBuf, CacheEntry>,
+    /// The entries of the files read in this run
+    used: Mutex<HashMap<PathBuf, CacheEntry>>,
+}
+
+impl MetadataCache {
+    /// Loads the cache from `file`. A missing or unreadable cache file is
+    /// treated as an empty cache.
+    pub fn load(file: Option<&Path>) -> MetadataCache {
+        let loaded = file
+            .and_then(|file| fs::read_to_string(file).ok())
+            .and_then(|content| serde_json::from_str::<CacheFile>(&content).ok())
+            .filter(|cache_file| cache_file.version == CACHE_VERSION)
+            .map(|cache_file| cache_file.entries)
+            .unwrap_or_default();
+
+        MetadataCache {
+            file: file.map(PathBuf::from),
+            loaded,
+            used: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the metadata of the file at `path`, reading the file only if
+    /// it changed since it was cached.
+    pub fn get(&self, path: &Path) -> Result<MdMetadata, Error> {
+        let Ok(file_metadata) = fs::metadata(path) else {
+            return read_metadata(path);
+        };
+        let modified = file_metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|modified| (modified.as_secs(), modified.subsec_nanos()))
+            .unwrap_or_default();
+        let size = file_metadata.len();
+
+        let entry = match self.loaded.get(path) {
+            Some(entry) if entry.modified == modified && entry.size == size => entry.clone(),
+            _ => CacheEntry {
+                modified,
+                size,
+                metadata: read_metadata(path)?,
+            },
+        };
+
+        let metadata = entry.metadata.clone();
+        self.used.lock().unwrap().insert(PathBuf::from(path), entry);
+        Ok(metadata)
+    }
+
+    /// Writes the entries of the files read in this run to the cache file,
+    /// if they changed. Entries of files that were not read are dropped.
+    pub fn save(self) -> Result<(), Error> {
+        let Some(file) = self.file else {
+            return Ok(());
+        };
+
+        let entries = self.used.into_inner().unwrap();
+        if entries == self.loaded && file.is_file() {
+            return Ok(());
+        }
+
+        let content = serde_json::to_string(&CacheFile {
+            version: CACHE_VERSION,
+            entries,
+        })?;
+
+        write_file_atomically(&file, &content)
+            .with_context(|| format!("Failed to write the cache file '{}'", file.display()))
+    }
+}
@@ -0,0 +1,393 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Error};
+use mdbook::book::{parse_summary, SummaryItem};
+use toml::value::{Table, Value};
+
+use crate::auto_gen_summary::config::{
+    AutoGenConfig, DIRECTORY_CONFIG_FILE, OPT_DRAFTS, OPT_ORDER, OPT_TITLES,
+};
+use crate::auto_gen_summary::hidden;
+use crate::auto_gen_summary::metadata::MetadataCache;
+use crate::auto_gen_summary::{prepare_entry_tree, relative_key, walk_dir, MdEntry, SUMMARY_FILE};
+
+/// The result of migrating hand-written summaries
+pub struct Migration {
+    /// The directory config files that were written
+    pub written_files: Vec<PathBuf>,
+    /// Entries of the summaries that the generator can't reproduce
+    pub unsupported: Vec<String>,
+}
+
+/// The options written to the config file of one directory
+#[derive(Default)]
+struct DirectoryManifest {
+    order: Vec<String>,
+    titles: BTreeMap<String, String>,
+    drafts: Vec<String>,
+}
+
+/// What the generator knows about an entry it finds on disk
+struct KnownEntry {
+    title: String,
+    /// The directory the entry was created from, if any
+    dir: Option<PathBuf>,
+}
+
+/// The state of the migration of one summary
+struct Migrator<'a> {
+    root_dir: &'a Path,
+    config: &'a AutoGenConfig,
+    /// The entries found by the generator, keyed by the path of their file
+    known_entries: HashMap<PathBuf, KnownEntry>,
+    /// The paths of the files listed in the summary
+    listed: HashSet<PathBuf>,
+    manifests: BTreeMap<PathBuf, DirectoryManifest>,
+    unsupported: Vec<String>,
+}
+
+/// Reads the hand-written `SUMMARY.md` in `source_dir` (or in every language
+/// root) and writes the `order`, `titles` and `drafts` options to the
+/// directory config files, so the generator reproduces the same structure.
+///
+/// Existing options in the directory config files are kept.
+pub fn migrate(source_dir: &Path, config: &AutoGenConfig) -> Result<Migration, Error> {
+    let mut config = config.clone();
+    config.read_only = true;
+
+    let mut roots = Vec::new();
+    if config.languages.is_empty() {
+        roots.push((PathBuf::from(source_dir), config));
+    } else {
+        for language in &config.languages {
+            roots.push((source_dir.join(language), config.for_language(language)?));
+        }
+    }
+
+    let mut migration = Migration {
+        written_files: Vec::new(),
+        unsupported: Vec::new(),
+    };
+
+    for (root, config) in roots {
+        let summary_path = root.join(SUMMARY_FILE);
+        let content = fs::read_to_string(&summary_path)
+            .with_context(|| format!("Failed to read '{}'", summary_path.display()))?;
+        let summary = parse_summary(&content)
+            .with_context(|| format!("Failed to parse '{}'", summary_path.display()))?;
+
+        let mut tree = walk_dir(
+            &root,
+            &root,
+            &config,
+            &MetadataCache::default(),
+            &mut Vec::new(),
+        )?;
+        if let Some(tree) = &mut tree {
+            // Hidden pages are never listed, whatever the summary says
+            hidden::take_hidden_pages(&root, tree);
+            prepare_entry_tree(&root, tree, &config);
+        }
+
+        let mut migrator = Migrator {
+            root_dir: &root,
+            config: &config,
+            known_entries: HashMap::new(),
+            listed: HashSet::new(),
+            manifests: BTreeMap::new(),
+            unsupported: Vec::new(),
+        };
+
+        if let Some(tree) = &tree {
+            for child in &tree.children {
+                migrator.add_known_entries(child);
+            }
+        }
+
+        if !summary.prefix_chapters.is_empty() || !summary.suffix_chapters.is_empty() {
+            migrator.unsupported.push(format!(
+                "{}: Prefix and suffix chapters become numbered chapters",
+                summary_path.display()
+            ));
+        }
+
+        let items: Vec<&SummaryItem> = summary
+            .prefix_chapters
+            .iter()
+            .chain(&summary.numbered_chapters)
+            .chain(&summary.suffix_chapters)
+            .collect();
+
+        migrator.migrate_items(&root, tree.as_ref(), &items);
+        migrator.report_unlisted(tree.as_ref());
+
+        for (dir, manifest) in migrator.manifests {
+            migration
+                .written_files
+                .push(write_manifest(&dir, manifest)?);
+        }
+        migration.unsupported.append(&mut migrator.unsupported);
+    }
+
+    Ok(migration)
+}
+
+impl Migrator<'_> {
+    fn add_known_entries(&mut self, entry: &MdEntry) {
+        if let Some(path) = &entry.path {
+            self.known_entries.insert(
+                path.clone(),
+                KnownEntry {
+                    title: entry.title.clone(),
+                    dir: if entry.is_dir {
+                        Some(entry.sorting_path.clone())
+                    } else {
+                        None
+                    },
+                },
+            );
+        }
+
+        for child in &entry.children {
+            self.add_known_entries(child);
+        }
+    }
+
+    /// Adds the items listed under the directory `dir` to its manifest
+    fn migrate_items(&mut self, dir: &Path, root: Option<&MdEntry>, items: &[&SummaryItem]) {
+        for item in items {
+            let link = match item {
+                SummaryItem::Link(link) => link,
+                SummaryItem::Separator => continue,
+                SummaryItem::PartTitle(title) => {
+                    self.unsupported
+                        .push(format!("Part title '{}' can't be expressed", title));
+                    continue;
+                }
+            };
+            let nested_items: Vec<&SummaryItem> = link.nested_items.iter().collect();
+
+            let Some(location) = &link.location else {
+                if !nested_items.is_empty() {
+                    self.unsupported.push(format!(
+                        "Draft '{}' has nested chapters, which can't be expressed",
+                        link.name
+                    ));
+                    continue;
+                }
+
+                let manifest = self.manifests.entry(PathBuf::from(dir)).or_default();
+                manifest.order.push(link.name.clone());
+                manifest.drafts.push(link.name.clone());
+                continue;
+            };
+
+            let Some(path) = self.resolve(location) else {
+                self.unsupported.push(format!(
+                    "'{}' links to '{}', which is outside the source directory or doesn't exist",
+                    link.name,
+                    location.display()
+                ));
+                continue;
+            };
+            self.listed.insert(path.clone());
+
+            // The root index is always listed first, with a fixed title
+            if root.and_then(|root| root.path.as_ref()) == Some(&path) {
+                let root_title = &root.unwrap().title;
+                if dir != self.root_dir || !nested_items.is_empty() || link.name != *root_title {
+                    self.unsupported.push(format!(
+                        "'{}' is the root index file, which is always listed first as '{}'",
+                        link.name, root_title
+                    ));
+                }
+                continue;
+            }
+
+            let (entry_dir, default_title) = match self.known_entries.get(&path) {
+                Some(known) => (known.dir.clone(), Some(known.title.clone())),
+                None => {
+                    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                    let is_index = self
+                        .config
+                        .directory_index_names
+                        .iter()
+                        .any(|name| *name == file_name);
+
+                    (path.parent().filter(|_| is_index).map(PathBuf::from), None)
+                }
+            };
+
+            // The path the entry is named after in its directory
+            let entry_path = entry_dir.clone().unwrap_or_else(|| path.clone());
+            if entry_path.parent() != Some(dir) {
+                self.unsupported.push(format!(
+                    "'{}' is listed under '{}' but is not in that directory, which can't be expressed",
+                    relative_key(self.root_dir, &path),
+                    relative_key(self.root_dir, dir)
+                ));
+                continue;
+            }
+
+            let manifest = self.manifests.entry(PathBuf::from(dir)).or_default();
+            manifest.order.push(
+                entry_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+            );
+            if default_title.as_ref() != Some(&link.name) {
+                manifest
+                    .titles
+                    .insert(relative_key(self.root_dir, &entry_path), link.name.clone());
+            }
+
+            match entry_dir {
+                Some(entry_dir) => self.migrate_items(&entry_dir, root, &nested_items),
+                None if !nested_items.is_empty() => self.unsupported.push(format!(
+                    "'{}' is not a directory index but has nested chapters, which can't be expressed",
+                    relative_key(self.root_dir, &path)
+                )),
+                None => {}
+            }
+        }
+    }
+
+    /// Returns the path of a file linked from the summary, or `None` if it is
+    /// outside the source directory or doesn't exist
+    fn resolve(&self, location: &Path) -> Option<PathBuf> {
+        let mut path = PathBuf::from(self.root_dir);
+
+        for component in location.components() {
+            match component {
+                Component::Normal(name) => path.push(name),
+                Component::CurDir => {}
+                _ => return None,
+            }
+        }
+
+        if path.is_file() {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    /// Reports the files the generator lists that are not in the summary
+    fn report_unlisted(&mut self, root: Option<&MdEntry>) {
+        let mut unlisted: Vec<&PathBuf> = self
+            .known_entries
+            .keys()
+            .chain(root.and_then(|root| root.path.as_ref()))
+            .filter(|path| !self.listed.contains(*path))
+            .collect();
+        unlisted.sort();
+
+        for path in unlisted {
+            self.unsupported.push(format!(
+                "'{}' is not listed in '{}' and will be added by the generator",
+                relative_key(self.root_dir, path),
+                SUMMARY_FILE
+            ));
+        }
+    }
+}
+
+/// Merges the manifest into the config file of `dir` and returns the path
+/// of the file
+fn write_manifest(dir: &Path, manifest: DirectoryManifest) -> Result<PathBuf, Error> {
+    let config_file_path = dir.join(DIRECTORY_CONFIG_FILE);
+
+    let mut table = if config_file_path.is_file() {
+        let content = fs::read_to_string(&config_file_path)
+            .with_context(|| format!("Failed to read '{}'", config_file_path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse '{}'", config_file_path.display()))?
+    } else {
+        Table::new()
+    };
+
+    let to_array = |items: Vec<String>| Value::Array(items.into_iter().map(Value::from).collect());
+
+    table.insert(String::from(OPT_ORDER), to_array(manifest.order));
+
+    if manifest.drafts.is_empty() {
+        table.remove(OPT_DRAFTS);
+    } else {
+        table.insert(String::from(OPT_DRAFTS), to_array(manifest.drafts));
+    }
+
+    if !manifest.titles.is_empty() {
+        let titles = table
+            .entry(String::from(OPT_TITLES))
+            .or_insert_with(|| Value::Table(Table::new()));
+        if let Value::Table(titles) = titles {
+            for (path, title) in manifest.titles {
+                titles.insert(path, Value::from(title));
+            }
+        }
+    }
+
+    let content = toml::to_string_pretty(&table)
+        .with_context(|| format!("Failed to serialize '{}'", config_file_path.display()))?;
+    fs::write(&config_file_path, content)
+        .with_context(|| format!("Failed to write '{}'", config_file_path.display()))?;
+
+    Ok(config_file_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_the_order_titles_and_drafts_of_the_summary() {
+        let source_dir =
+            std::env::temp_dir().join(format!("auto-gen-summary-migrate-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&source_dir);
+        fs::create_dir_all(source_dir.join("guide")).unwrap();
+        for (path, content) in [
+            ("README.md", "# Home\n"),
+            ("alpha.md", "# Alpha\n"),
+            ("beta.md", "# Beta\n"),
+            ("guide/README.md", "# Guide\n"),
+            ("guide/basics.md", "# Basics\n"),
+            ("unlisted.md", "# Unlisted\n"),
+            (
+                "SUMMARY.md",
+                "# Summary\n\n- [Welcome](README.md)\n- [Second](beta.md)\n- [alpha.md](alpha.md)\n- [Later]()\n- [guide](guide/README.md)\n    - [basics.md](guide/basics.md)\n",
+            ),
+        ] {
+            fs::write(source_dir.join(path), content).unwrap();
+        }
+
+        let migration = migrate(&source_dir, &AutoGenConfig::new());
+        let root_manifest = fs::read_to_string(source_dir.join(DIRECTORY_CONFIG_FILE));
+        let guide_manifest =
+            fs::read_to_string(source_dir.join("guide").join(DIRECTORY_CONFIG_FILE));
+        fs::remove_dir_all(&source_dir).unwrap();
+
+        let migration = migration.unwrap();
+        assert_eq!(migration.written_files.len(), 2);
+        assert_eq!(
+            migration.unsupported,
+            vec!["'unlisted.md' is not listed in 'SUMMARY.md' and will be added by the generator"]
+        );
+
+        let root_manifest: Table = toml::from_str(&root_manifest.unwrap()).unwrap();
+        let expected: Table = toml::from_str(
+            "order = [\"beta.md\", \"alpha.md\", \"Later\", \"guide\"]\n\
+             drafts = [\"Later\"]\n\
+             titles = { \"beta.md\" = \"Second\" }",
+        )
+        .unwrap();
+        assert_eq!(root_manifest, expected);
+
+        let guide_manifest: Table = toml::from_str(&guide_manifest.unwrap()).unwrap();
+        let expected: Table = toml::from_str("order = [\"basics.md\"]").unwrap();
+        assert_eq!(guide_manifest, expected);
+    }
+}
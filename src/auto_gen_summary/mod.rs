@@ -1,19 +1,38 @@
-use hex;
-use md5::{Digest, Md5};
-use mdbook::book::Book;
+use anyhow::Context;
+use mdbook::book::{parse_summary, Book};
 use mdbook::errors::Error;
 use mdbook::preprocess::{Preprocessor, PreprocessorContext};
 use mdbook::utils;
 use mdbook::MDBook;
+use rayon::prelude::*;
+use std::collections::BTreeMap;
 use std::ffi::OsString;
 use std::fs;
-use std::io::prelude::*;
-use std::io::{BufReader, BufWriter};
-use std::path::{Path, PathBuf};
-
-use crate::auto_gen_summary::config::{AutoGenConfig, DirectoryWithoutIndexBehavior};
+use std::path::{Component, Path, PathBuf};
+
+use crate::auto_gen_summary::config::{
+    AutoGenConfig, DirectoryWithoutIndexBehavior, DraftBehavior, DuplicateIndexBehavior,
+    MaxDepthBehavior, SortOrder, OPT_EXCLUDE, OPT_RESERVED_FILES,
+};
+use crate::auto_gen_summary::export::{ExportFormat, Toc};
+use crate::auto_gen_summary::hidden::HiddenPage;
+use crate::auto_gen_summary::links::SummaryLink;
+use crate::auto_gen_summary::lint::{check_file, Problem, ProblemKind};
+use crate::auto_gen_summary::metadata::MetadataCache;
+use crate::auto_gen_summary::verify::SummaryChapter;
 
 pub mod config;
+pub mod export;
+pub mod hidden;
+pub mod language;
+mod links;
+pub mod lint;
+pub mod metadata;
+pub mod migrate;
+mod roots;
+mod sort;
+mod transform;
+mod verify;
 
 pub const PREPROCESSOR_NAME: &str = "auto-gen-summary";
 const SUMMARY_FILE: &str = "SUMMARY.md";
@@ -22,8 +41,22 @@ pub struct MdEntry {
     title: String,
     /// The link that the entry link point to. None corresponds to a draft entry.
     path: Option<PathBuf>,
+    /// The URL of an extra entry that links outside the book. Such entries
+    /// have no path and are written as drafts in `SUMMARY.md`.
+    url: Option<String>,
     /// A path used only for sorting. Must not be empty.
     sorting_path: PathBuf,
+    /// Whether the entry was created from a directory.
+    is_dir: bool,
+    /// The front matter of the file (or directory index file).
+    front_matter: BTreeMap<String, String>,
+    /// How the children of this entry are sorted.
+    sort_order: SortOrder,
+    /// Names of children that are listed first, in this order.
+    order: Vec<String>,
+    /// Whether the entry is built but not listed in the summary. The
+    /// children of a hidden entry are hidden too.
+    hidden: bool,
     children: Vec<MdEntry>,
 }
 
@@ -35,25 +68,75 @@ impl AutoGenSummary {
     }
 }
 
+impl Default for AutoGenSummary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Preprocessor for AutoGenSummary {
     fn name(&self) -> &str {
         PREPROCESSOR_NAME
     }
 
     fn run(&self, ctx: &PreprocessorContext, _book: Book) -> Result<Book, Error> {
-        let mut config = AutoGenConfig::new();
-        config.apply_config(&ctx.config)?;
-
+        let config = AutoGenConfig::from_book(&ctx.root, &ctx.config)?;
         let source_dir = ctx.root.join(&ctx.config.book.src);
 
-        gen_summary(&source_dir, &config);
+        let mut summaries = render_summaries(&source_dir, &config)?;
+        for summary in &summaries {
+            write_summary(&summary.path, &summary.content)?;
+        }
 
-        match MDBook::load(&ctx.root) {
-            Ok(mdbook) => Ok(mdbook.book),
-            Err(e) => {
-                panic!("{}", e);
-            }
+        if let Some(export_file) = &config.export_file {
+            // Only the summary the book is built from is exported
+            let book_language = config.book_language(ctx.config.book.language.as_deref());
+            let toc: Vec<Toc> = summaries
+                .iter()
+                .map(|summary| &summary.toc)
+                .filter(|toc| toc.language.as_ref() == book_language)
+                .cloned()
+                .collect();
+            export_toc(export_file, &toc)?;
+        }
+
+        // The summary of the renderer is only used to build the book
+        let mut book_config = config;
+        if let Some(renderer_config) = book_config.for_renderer(&ctx.renderer)? {
+            summaries = render_summaries(&source_dir, &renderer_config)?;
+            book_config = renderer_config;
+        }
+
+        let book_language = book_config
+            .book_language(ctx.config.book.language.as_deref())
+            .cloned();
+        let summary = summaries
+            .into_iter()
+            .find(|summary| summary.toc.language == book_language)
+            .expect("A summary is generated for the language of the book");
+
+        // Build the book from the summary of the selected language root
+        let mut mdbook_config = ctx.config.clone();
+        if let Some(language) = &book_language {
+            mdbook_config.book.src = mdbook_config.book.src.join(language);
         }
+
+        let parsed_summary = parse_summary(&summary.content)
+            .with_context(|| format!("Failed to parse '{}'", summary.path.display()))?;
+        let book_source_dir = ctx.root.join(&mdbook_config.book.src);
+        let mut book =
+            MDBook::load_with_config_and_summary(&ctx.root, mdbook_config, parsed_summary)?.book;
+        roots::mount_chapters(&mut book, &book_source_dir, &book_config);
+        hidden::add_hidden_pages(
+            &mut book,
+            &book_source_dir,
+            &summary.hidden_pages,
+            ctx,
+            &book_config,
+        )?;
+        links::add_links(&mut book, &summary.links, &ctx.renderer);
+
+        Ok(book)
     }
 
     fn supports_renderer(&self, renderer: &str) -> bool {
@@ -61,48 +144,193 @@ impl Preprocessor for AutoGenSummary {
     }
 }
 
-fn md5(buf: &String) -> String {
-    let mut hasher = Md5::new();
-    hasher.update(buf.as_bytes());
-    let f = hasher.finalize();
-    let md5_vec = f.as_slice();
-    let md5_string = hex::encode_upper(md5_vec);
+/// Writes the tables of contents to `path`, in the format matching the
+/// extension of the file.
+fn export_toc(path: &Path, tocs: &[Toc]) -> Result<(), Error> {
+    let Some(format) = ExportFormat::from_path(path) else {
+        anyhow::bail!(
+            "Unknown export format for '{}'. The file extension must be one of 'json', 'yaml', 'yml', or 'toml'",
+            path.display()
+        );
+    };
+
+    let content = format.serialize(tocs)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create '{}'", parent.display()))?;
+    }
+    fs::write(path, content).with_context(|| format!("Failed to write '{}'", path.display()))?;
 
-    return md5_string;
+    Ok(())
 }
 
-pub fn gen_summary(source_dir: &Path, config: &AutoGenConfig) {
-    let group = walk_dir(source_dir, config);
-    let mut lines = vec![String::from("# Summary\n")];
+/// A summary generated for the book, or for one language root
+pub struct GeneratedSummary {
+    /// The file the summary is written to
+    pub path: PathBuf,
+    pub content: String,
+    pub toc: Toc,
+    /// The pages that are built but not listed in the summary
+    pub hidden_pages: Vec<HiddenPage>,
+    /// The entries that link to a URL, which are drafts in the summary
+    pub links: Vec<SummaryLink>,
+}
+
+/// Generates `SUMMARY.md` for the book in `source_dir` (or the file set in
+/// the `output` option), or one for each language root if the `languages`
+/// option is set.
+///
+/// Returns the table of contents of every generated summary.
+pub fn gen_summary(source_dir: &Path, config: &AutoGenConfig) -> Result<Vec<Toc>, Error> {
+    let summaries = render_summaries(source_dir, config)?;
+
+    for summary in &summaries {
+        write_summary(&summary.path, &summary.content)?;
+    }
+
+    Ok(summaries.into_iter().map(|summary| summary.toc).collect())
+}
 
-    if let Some(mut group) = group {
-        if !config.index_first_line_as_directory_link_text {
-            group.title = String::from("Welcome");
+/// Generates the summary of the book in `source_dir`, or of every language
+/// root if the `languages` option is set, without writing them.
+pub fn render_summaries(
+    source_dir: &Path,
+    config: &AutoGenConfig,
+) -> Result<Vec<GeneratedSummary>, Error> {
+    if !config.languages.is_empty() {
+        if config.output.is_some() {
+            anyhow::bail!(
+                "The 'output' option can't be used with 'languages', every language root has its own summary"
+            );
+        }
+        if !config.extra_roots.is_empty() {
+            anyhow::bail!("The 'extra-roots' option can't be used with 'languages'");
         }
 
-        sort_entry_recursive(&mut group);
+        let cache = MetadataCache::load(config.cache_file.as_deref());
+        let summaries = language::render_language_summaries(source_dir, config, &cache)?;
+        cache.save()?;
+        return Ok(summaries);
+    }
+
+    let cache = MetadataCache::load(config.cache_file.as_deref());
+    let mut group = walk_dir(source_dir, source_dir, config, &cache, &mut Vec::new())?;
+    if let Some(group) = &mut group {
+        roots::add_extra_roots(source_dir, group, config, &cache, &mut Vec::new())?;
+    }
+    cache.save()?;
+
+    let mut hidden_pages = Vec::new();
+    if let Some(group) = &mut group {
+        hidden_pages = hidden::take_hidden_pages(source_dir, group);
+        hidden_pages.append(&mut prepare_entry_tree(source_dir, group, config));
+    }
+
+    let links = group.as_ref().map(links::collect_links).unwrap_or_default();
+    let toc = Toc::from_tree(source_dir, None, group.as_ref(), config);
 
+    let (content, chapters) = render_summary(source_dir, group, config);
+    verify::verify_summary(&content, &chapters)?;
+
+    Ok(vec![GeneratedSummary {
+        path: config.summary_path(source_dir),
+        content,
+        toc,
+        hidden_pages,
+        links,
+    }])
+}
+
+/// Applies the tree transformations set in the config to the root entry
+/// built by `walk_dir`, then sorts the tree.
+///
+/// Returns the pages that the transformations removed from the tree but
+/// that must still be built, as hidden pages.
+fn prepare_entry_tree(
+    source_dir: &Path,
+    group: &mut MdEntry,
+    config: &AutoGenConfig,
+) -> Vec<HiddenPage> {
+    let mut hidden_pages = Vec::new();
+
+    if !config.index_first_line_as_directory_link_text {
+        group.title = String::from("Welcome");
+    }
+
+    if config.draft_behavior == DraftBehavior::Omit {
+        transform::omit_drafts(group);
+    }
+
+    if !config.flatten_directories.is_empty() {
+        transform::flatten_directories(source_dir, group, &config.flatten_directories);
+    }
+
+    if config.collapse_single_child_directories {
+        for child in &mut group.children {
+            transform::collapse_single_child_directories(source_dir, child, &mut hidden_pages);
+        }
+    }
+
+    sort::sort_entry_recursive(source_dir, group);
+    roots::place_extra_roots(source_dir, group, config);
+    links::add_extra_entries(source_dir, group, config);
+
+    if let (Some(max_depth), MaxDepthBehavior::Hide) =
+        (config.max_depth, &config.max_depth_behavior)
+    {
+        let mut deep_pages = Vec::new();
+        for child in &mut group.children {
+            transform::hide_deep_entries(source_dir, child, 0, max_depth, &mut deep_pages);
+        }
+        if !deep_pages.is_empty() && !config.read_only {
+            let paths: Vec<String> = deep_pages
+                .iter()
+                .map(|page| page.path.to_string_lossy().replace('\\', "/"))
+                .collect();
+            eprintln!(
+                "Warn: The following pages are deeper than 'max-depth' and are only built as hidden pages:\n  - {}",
+                paths.join("\n  - ")
+            );
+        }
+        hidden_pages.append(&mut deep_pages);
+    }
+
+    hidden_pages
+}
+
+/// Generates the content of `SUMMARY.md` from the root entry. Also returns
+/// the chapters the content is expected to parse to.
+fn render_summary(
+    source_dir: &Path,
+    group: Option<MdEntry>,
+    config: &AutoGenConfig,
+) -> (String, Vec<SummaryChapter>) {
+    let mut lines = vec![String::from("# Summary\n")];
+    let mut chapters = Vec::new();
+
+    if let Some(group) = group {
         if let Some(root_index_path) = group.path {
-            lines.push(generate_summary_line(
-                0,
-                &group.title,
-                &RelativizedLink::from(source_dir, &Some(root_index_path)),
-            ));
+            let link = RelativizedLink::from(source_dir, &Some(root_index_path));
+            lines.push(generate_summary_line(0, &group.title, &link));
+            chapters.push(SummaryChapter::new(0, &group.title, &link));
         }
 
         // This variable is used to insert "---" lines *around* top-level directories
         let mut last_was_dir = false;
+        // Entries left out of the summary because they are deeper than the max depth
+        let mut hidden_entries = Vec::new();
 
         for child in group.children {
             let entry_name = if let Some(path) = &child.path {
-                path.file_name().map(|p| OsString::from(p))
+                path.file_name().map(OsString::from)
             } else {
                 None
             };
-            let is_dir = child.children.len() > 0;
+            let is_dir = !child.children.is_empty();
 
-            if !is_dir && entry_name == Some(OsString::from(SUMMARY_FILE)) {
-                continue; // filter out summary file in first level directory
+            if !is_dir && entry_name == Some(config.summary_file_name()) {
+                continue; // never list the generated summary, even if it is not reserved
             }
 
             if last_was_dir || is_dir {
@@ -110,7 +338,21 @@ pub fn gen_summary(source_dir: &Path, config: &AutoGenConfig) {
             }
             last_was_dir = is_dir;
 
-            lines.append(&mut gen_summary_for_entry(source_dir, 0, &child, config));
+            lines.append(&mut gen_summary_for_entry(
+                source_dir,
+                0,
+                &child,
+                config,
+                &mut hidden_entries,
+                &mut chapters,
+            ));
+        }
+
+        if !hidden_entries.is_empty() {
+            eprintln!(
+                "Warn: The following entries are deeper than 'max-depth' and were left out of the summary:\n  - {}",
+                hidden_entries.join("\n  - ")
+            );
         }
     } else {
         let mut suggested_generate_file_path = PathBuf::from(source_dir);
@@ -121,79 +363,119 @@ Suggested fixes:
   - Create the file '{}'
   - Set the option 'dir-without-index-behavior' to 'draft' or 'gen-stub-index'
   - Set the option 'dir-index-names' to the name of a file in the directory '{}'",
-            suggested_generate_file_path.to_string_lossy().to_string(),
-            source_dir.to_string_lossy().to_string()
+            suggested_generate_file_path.to_string_lossy(),
+            source_dir.to_string_lossy()
         );
     }
 
-    let buff: String = lines.join("\n");
-
-    let new_md5_string = md5(&buff);
-
-    let mut summary_file_path = PathBuf::from(source_dir);
-    summary_file_path.push(SUMMARY_FILE);
-
-    let summary_file = std::fs::OpenOptions::new()
-        .write(true)
-        .read(true)
-        .create(true)
-        .open(&summary_file_path)
-        .unwrap();
-
-    let mut old_summary_file_content = String::new();
-    let mut summary_file_reader = BufReader::new(summary_file);
-    summary_file_reader
-        .read_to_string(&mut old_summary_file_content)
-        .unwrap();
-
-    let old_md5_string = md5(&old_summary_file_content);
+    (lines.join("\n"), chapters)
+}
 
-    if new_md5_string == old_md5_string {
-        return;
+/// Writes `buff` to the summary file at `summary_file_path` if the content
+/// of the file changed.
+fn write_summary(summary_file_path: &Path, buff: &str) -> Result<(), Error> {
+    if summary_file_path.is_file() {
+        let old_summary = fs::read_to_string(summary_file_path)
+            .with_context(|| format!("Failed to read '{}'", summary_file_path.display()))?;
+        if old_summary == buff {
+            return Ok(());
+        }
     }
 
-    let summary_file = std::fs::OpenOptions::new()
-        .write(true)
-        .read(true)
-        .create(true)
-        .truncate(true)
-        .open(&summary_file_path)
-        .unwrap();
-    let mut summary_file_writer = BufWriter::new(summary_file);
-    summary_file_writer.write_all(buff.as_bytes()).unwrap();
+    write_file_atomically(summary_file_path, buff)
 }
 
-/// Recursively sorts the entries by path
-pub fn sort_entry_recursive(entry: &mut MdEntry) {
-    entry
-        .children
-        .sort_by(|a, b| a.sorting_path.cmp(&b.sorting_path));
-
-    for mut child in &mut entry.children {
-        sort_entry_recursive(&mut child);
+/// Writes `content` to a temporary file next to `file_path`, then renames it
+/// to `file_path`, so readers never see a partly written file. Creates the
+/// parent directories if needed.
+fn write_file_atomically(file_path: &Path, content: &str) -> Result<(), Error> {
+    let dir = match file_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create '{}'", dir.display()))?;
+
+    let temp_file_path = dir.join(format!(
+        ".{}.{}.tmp",
+        file_path.file_name().unwrap_or_default().to_string_lossy(),
+        std::process::id()
+    ));
+
+    let result = fs::write(&temp_file_path, content)
+        .with_context(|| format!("Failed to write '{}'", temp_file_path.display()))
+        .and_then(|_| {
+            fs::rename(&temp_file_path, file_path)
+                .with_context(|| format!("Failed to replace '{}'", file_path.display()))
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_file_path);
     }
+
+    result
 }
 
+/// Generates the summary lines for an entry and its children.
+///
+/// Children deeper than the `max_depth` option are handled according to the
+/// `max_depth_behavior` option. Entries that are left out are added to
+/// `hidden_entries`, and the listed ones to `chapters`.
 fn gen_summary_for_entry(
     root_dir: &Path,
     depth: usize,
     md_entry: &MdEntry,
     config: &AutoGenConfig,
+    hidden_entries: &mut Vec<String>,
+    chapters: &mut Vec<SummaryChapter>,
 ) -> Vec<String> {
     let mut lines: Vec<String> = Vec::new();
 
     let path = RelativizedLink::from(root_dir, &md_entry.path);
 
     lines.push(generate_summary_line(depth, &md_entry.title, &path));
+    chapters.push(SummaryChapter::new(depth, &md_entry.title, &path));
+
+    let children_depth = match config.max_depth {
+        Some(max_depth) if depth + 1 >= max_depth => match config.max_depth_behavior {
+            // Hidden entries are already removed by `prepare_entry_tree`
+            MaxDepthBehavior::Omit | MaxDepthBehavior::Hide => {
+                for child in &md_entry.children {
+                    collect_hidden_entries(root_dir, child, hidden_entries);
+                }
+                return lines;
+            }
+            MaxDepthBehavior::Flatten => depth,
+        },
+        _ => depth + 1,
+    };
 
     for child in &md_entry.children {
-        let mut line = gen_summary_for_entry(root_dir, depth + 1, &child, config);
+        let mut line = gen_summary_for_entry(
+            root_dir,
+            children_depth,
+            child,
+            config,
+            hidden_entries,
+            chapters,
+        );
         lines.append(&mut line);
     }
 
     lines
 }
 
+fn collect_hidden_entries(root_dir: &Path, md_entry: &MdEntry, hidden_entries: &mut Vec<String>) {
+    let path = RelativizedLink::from(root_dir, &md_entry.path);
+    hidden_entries.push(if path.0.is_empty() {
+        md_entry.title.clone()
+    } else {
+        path.0
+    });
+
+    for child in &md_entry.children {
+        collect_hidden_entries(root_dir, child, hidden_entries);
+    }
+}
+
 /// Struct that marks a string as a relativized link.
 ///
 /// This struct was made to prevents insertion of absolute paths into
@@ -203,67 +485,170 @@ struct RelativizedLink(String);
 impl RelativizedLink {
     fn from(root_dir: &Path, path: &Option<PathBuf>) -> RelativizedLink {
         RelativizedLink(if let Some(path) = path {
-            path.strip_prefix(root_dir)
-                .unwrap()
-                .to_string_lossy()
-                .to_string()
+            relative_path(root_dir, path).to_string_lossy().to_string()
         } else {
             String::from("")
         })
     }
 }
 
+/// Returns the path of `path` relative to `root_dir`. Paths outside of
+/// `root_dir` (such as the files of an extra root) start with `..`.
+fn relative_path(root_dir: &Path, path: &Path) -> PathBuf {
+    if let Ok(relative) = path.strip_prefix(root_dir) {
+        return PathBuf::from(relative);
+    }
+
+    let root_dir = std::path::absolute(root_dir).unwrap_or_else(|_| PathBuf::from(root_dir));
+    let path = std::path::absolute(path).unwrap_or_else(|_| PathBuf::from(path));
+    let root_components: Vec<Component> = root_dir.components().collect();
+    let components: Vec<Component> = path.components().collect();
+    let common = root_components
+        .iter()
+        .zip(&components)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..root_components.len() {
+        relative.push("..");
+    }
+    relative.extend(&components[common..]);
+    relative
+}
+
 fn generate_summary_line(indentation_level: usize, title: &str, link: &RelativizedLink) -> String {
     format!(
         "{}* [{}]({})",
         " ".repeat(4 * indentation_level),
-        title,
+        escape_title(title),
         &link.0
     )
 }
 
-fn get_title(md_file_path: &Path) -> String {
-    let md_file = std::fs::File::open(md_file_path).unwrap();
-    let mut md_file_content = String::new();
-    let mut md_file_reader = BufReader::new(md_file);
-    md_file_reader.read_to_string(&mut md_file_content).unwrap();
-    let lines = md_file_content.split("\n");
-
-    let mut title: String = "".to_string();
-    let mut first_h1_line = "";
-    for line in lines {
-        if line.starts_with("# ") {
-            first_h1_line = line.trim_matches('#').trim();
-            break;
+/// Escapes the characters of `title` that mdbook's summary parser would
+/// read as markdown, such as code spans or HTML tags
+fn escape_title(title: &str) -> String {
+    let mut escaped = String::with_capacity(title.len());
+    for c in title.chars() {
+        if matches!(c, '[' | ']' | '`' | '*' | '_' | '<' | '>' | '&' | '\\') {
+            escaped.push('\\');
         }
+        escaped.push(c);
     }
+    escaped
+}
+
+/// Returns the path of `path` relative to `root_dir`, with `/` as the
+/// separator. Used to look up paths listed in the config.
+fn relative_key(root_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(root_dir)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
 
-    if first_h1_line.len() > 0 {
-        title = first_h1_line.to_string();
+/// Builds the entry of a markdown file that is not a directory index
+fn file_entry(
+    root_dir: &Path,
+    path: PathBuf,
+    file_name: String,
+    config: &AutoGenConfig,
+    cache: &MetadataCache,
+    problems: &mut Vec<Problem>,
+) -> Result<MdEntry, Error> {
+    check_file(&path, problems);
+    let metadata = cache.get(&path)?;
+
+    if config.first_line_as_link_text && metadata.title.is_empty() {
+        problems.push(Problem::new(
+            ProblemKind::MissingHeading,
+            path.clone(),
+            "File has no h1 heading to use as the title",
+        ));
     }
 
-    return title;
+    let hidden = hidden::is_hidden(root_dir, &path, &metadata.front_matter, config);
+
+    Ok(MdEntry {
+        title: if let Some(title) = config.titles.get(&relative_key(root_dir, &path)) {
+            title.clone()
+        } else if config.first_line_as_link_text && !metadata.title.is_empty() {
+            metadata.title
+        } else {
+            file_name
+        },
+        path: Some(path.clone()),
+        url: None,
+        sorting_path: path,
+        is_dir: false,
+        front_matter: metadata.front_matter,
+        sort_order: config.sort_order,
+        order: Vec::new(),
+        hidden,
+        children: Vec::new(),
+    })
 }
 
-fn walk_dir(dir: &Path, config: &AutoGenConfig) -> Option<MdEntry> {
-    let read_dir = fs::read_dir(dir).unwrap();
+/// Builds the tree of entries for `dir`. Problems found along the way (see
+/// the `lint` module) are added to `problems`.
+fn walk_dir(
+    root_dir: &Path,
+    dir: &Path,
+    inherited_config: &AutoGenConfig,
+    cache: &MetadataCache,
+    problems: &mut Vec<Problem>,
+) -> Result<Option<MdEntry>, Error> {
+    let directory_config = inherited_config.for_directory(dir)?;
+    let config = directory_config.as_ref().unwrap_or(inherited_config);
+
+    let read_dir =
+        fs::read_dir(dir).with_context(|| format!("Failed to read '{}'", dir.display()))?;
 
     let mut child_directories = Vec::new();
-    let mut result_children = Vec::new();
-    let mut index_entry = None;
+    let mut md_files = Vec::new();
+    let mut index_files = Vec::new();
 
     for entry in read_dir {
-        let entry = entry.unwrap();
+        let entry = entry.with_context(|| format!("Failed to read '{}'", dir.display()))?;
 
-        if entry.file_type().unwrap().is_dir() {
-            child_directories.push(entry);
+        let file_name = entry.file_name();
+        let Some(file_name) = file_name.to_str() else {
+            problems.push(Problem::new(
+                ProblemKind::NonUtf8Name,
+                entry.path(),
+                "Name is not valid UTF-8, so it is skipped",
+            ));
+            continue;
+        };
+        let file_name = file_name.to_string();
+
+        if config.is_excluded(root_dir, &entry.path()) {
+            lint::report_filtered_files(&entry.path(), OPT_EXCLUDE, problems);
             continue;
         }
 
-        let file_name = entry.file_name();
-        let file_name = file_name.to_str().unwrap().to_string();
-        if config.directory_index_names.contains(&file_name) {
-            let _ = index_entry.insert(entry.path());
+        let file_type = entry
+            .file_type()
+            .with_context(|| format!("Failed to read the type of '{}'", entry.path().display()))?;
+        if file_type.is_dir() {
+            child_directories.push(entry.path());
+            continue;
+        }
+
+        if config.is_reserved_file(root_dir, &entry.path()) {
+            if !lint::is_summary_file(root_dir, &entry.path(), config) {
+                lint::report_filtered_files(&entry.path(), OPT_RESERVED_FILES, problems);
+            }
+            continue;
+        }
+
+        if let Some(priority) = config
+            .directory_index_names
+            .iter()
+            .position(|name| *name == file_name)
+        {
+            index_files.push((priority, entry.path(), file_name));
             continue;
         }
 
@@ -271,33 +656,95 @@ fn walk_dir(dir: &Path, config: &AutoGenConfig) -> Option<MdEntry> {
             continue;
         }
 
-        let title = get_title(&entry.path());
+        md_files.push((entry.path(), file_name));
+    }
 
-        let md = MdEntry {
-            title: if config.first_line_as_link_text && title.len() > 0 {
-                title
-            } else {
-                file_name.to_string()
-            },
-            path: Some(entry.path()),
-            sorting_path: entry.path(),
-            children: Vec::new(),
-        };
+    // The files are read in parallel. Every file collects its own problems,
+    // which are added in the order of the files.
+    let file_entries: Vec<_> = md_files
+        .into_par_iter()
+        .map(|(path, file_name)| {
+            let mut file_problems = Vec::new();
+            let entry = file_entry(root_dir, path, file_name, config, cache, &mut file_problems);
+            (entry, file_problems)
+        })
+        .collect();
 
-        result_children.push(md);
+    let mut result_children = Vec::new();
+    for (entry, mut file_problems) in file_entries {
+        result_children.push(entry?);
+        problems.append(&mut file_problems);
+    }
+
+    // The index file with the highest priority name is used
+    index_files.sort_by_key(|(priority, _, _)| *priority);
+    let mut index_files = index_files.into_iter();
+    let mut index_entry = index_files.next().map(|(_, path, _)| path);
+
+    for (_, shadowed_index_file, file_name) in index_files {
+        let index_file_name = index_entry
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        match config.duplicate_index_behavior {
+            DuplicateIndexBehavior::Chapter => {
+                result_children.push(file_entry(
+                    root_dir,
+                    shadowed_index_file,
+                    file_name,
+                    config,
+                    cache,
+                    problems,
+                )?);
+                continue;
+            }
+            // Commands that only report on the book report it as a problem
+            // instead
+            DuplicateIndexBehavior::Warn if !config.read_only => {
+                eprintln!(
+                    "Warn: '{}' is left out of the summary because '{}' is the index of the directory.",
+                    shadowed_index_file.display(),
+                    index_file_name
+                );
+            }
+            DuplicateIndexBehavior::Drop | DuplicateIndexBehavior::Warn => {}
+        }
+
+        problems.push(Problem::new(
+            ProblemKind::ShadowedIndexFile,
+            shadowed_index_file,
+            format!(
+                "Directory has multiple index files, so this file is ignored in favor of '{}'",
+                index_file_name
+            ),
+        ));
     }
 
     if index_entry.is_none() {
         match config.directory_without_index_behavior {
+            DirectoryWithoutIndexBehavior::GenerateStubIndex if config.read_only => {
+                // list the directory as a draft instead of creating the index
+            }
             DirectoryWithoutIndexBehavior::GenerateStubIndex => {
                 let mut index_entry_path = PathBuf::from(dir);
                 index_entry_path.push(&config.generated_directory_index_name);
-                let _ = utils::fs::create_file(&index_entry_path).unwrap();
+                utils::fs::create_file(&index_entry_path).with_context(|| {
+                    format!("Failed to create '{}'", index_entry_path.display())
+                })?;
                 index_entry = Some(index_entry_path);
             }
             DirectoryWithoutIndexBehavior::Ignore => {
                 // ignore directory
-                return None;
+                problems.push(Problem::new(
+                    ProblemKind::IgnoredDirectory,
+                    PathBuf::from(dir),
+                    "Directory has no index file, so it is ignored",
+                ));
+                lint::report_orphaned_files(root_dir, dir, config, problems);
+                return Ok(None);
             }
             DirectoryWithoutIndexBehavior::Draft => {
                 // continue with no index
@@ -305,38 +752,246 @@ fn walk_dir(dir: &Path, config: &AutoGenConfig) -> Option<MdEntry> {
         }
     }
 
-    for child_dir in child_directories {
-        let g = walk_dir(&child_dir.path(), &config);
-        if let Some(g) = g {
+    // Subdirectories are walked in parallel, like the files
+    let child_results: Vec<_> = child_directories
+        .into_par_iter()
+        .map(|child_dir| {
+            let mut child_problems = Vec::new();
+            let g = walk_dir(root_dir, &child_dir, config, cache, &mut child_problems);
+            (g, child_problems)
+        })
+        .collect();
+
+    for (g, mut child_problems) in child_results {
+        problems.append(&mut child_problems);
+        if let Some(g) = g? {
             result_children.push(g);
         }
     }
 
-    let dir_name_as_string = dir.file_name().unwrap().to_string_lossy().to_string();
+    // The order and drafts only apply to the directory whose config sets them
+    let (order, drafts) = match &directory_config {
+        Some(directory_config) => (&directory_config.order, &directory_config.drafts),
+        None if dir == root_dir => (&inherited_config.order, &inherited_config.drafts),
+        None => (&Vec::new(), &Vec::new()),
+    };
 
-    Some(match index_entry {
-        Some(index_entry) => MdEntry {
-            title: {
-                if config.index_first_line_as_directory_link_text {
-                    let t = get_title(&index_entry);
-                    if t.len() > 0 {
-                        t
+    for draft in drafts {
+        result_children.push(MdEntry {
+            title: draft.clone(),
+            path: None,
+            url: None,
+            sorting_path: dir.join(draft),
+            is_dir: false,
+            front_matter: BTreeMap::new(),
+            sort_order: config.sort_order,
+            order: Vec::new(),
+            hidden: false,
+            children: Vec::new(),
+        });
+    }
+
+    let order = order.clone();
+    let dir_name_as_string = dir
+        .file_name()
+        .with_context(|| format!("'{}' has no directory name", dir.display()))?
+        .to_string_lossy()
+        .to_string();
+    let title_override = config.titles.get(&relative_key(root_dir, dir)).cloned();
+
+    Ok(Some(match index_entry {
+        Some(index_entry) => {
+            check_file(&index_entry, problems);
+            let metadata = cache.get(&index_entry)?;
+
+            if config.index_first_line_as_directory_link_text && metadata.title.is_empty() {
+                problems.push(Problem::new(
+                    ProblemKind::MissingHeading,
+                    index_entry.clone(),
+                    "Index file has no h1 heading to use as the directory title",
+                ));
+            }
+
+            let hidden = hidden::is_hidden(root_dir, &index_entry, &metadata.front_matter, config);
+
+            MdEntry {
+                title: {
+                    if let Some(title) = title_override {
+                        title
+                    } else if config.index_first_line_as_directory_link_text
+                        && !metadata.title.is_empty()
+                    {
+                        metadata.title
                     } else {
                         dir_name_as_string
                     }
-                } else {
-                    dir_name_as_string
-                }
-            },
-            path: Some(index_entry),
-            sorting_path: PathBuf::from(dir),
-            children: result_children,
-        },
+                },
+                path: Some(index_entry),
+                url: None,
+                sorting_path: PathBuf::from(dir),
+                is_dir: true,
+                front_matter: metadata.front_matter,
+                sort_order: config.sort_order,
+                order,
+                hidden,
+                children: result_children,
+            }
+        }
         None => MdEntry {
-            title: dir_name_as_string,
+            title: title_override.unwrap_or(dir_name_as_string),
             path: None,
+            url: None,
             sorting_path: PathBuf::from(dir),
+            is_dir: true,
+            front_matter: BTreeMap::new(),
+            sort_order: config.sort_order,
+            order,
+            hidden: false,
             children: result_children,
         },
-    })
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a directory under the temporary directory with `files`, given
+    /// as paths relative to it and their content
+    fn temp_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("auto-gen-summary-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    fn walk(dir: &Path, config: &AutoGenConfig) -> (MdEntry, Vec<Problem>) {
+        let mut problems = Vec::new();
+        let tree = walk_dir(dir, dir, config, &MetadataCache::default(), &mut problems);
+        (tree.unwrap().unwrap(), problems)
+    }
+
+    fn file_names(entry: &MdEntry) -> Vec<String> {
+        entry
+            .children
+            .iter()
+            .filter_map(|child| child.path.as_ref()?.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn the_index_name_with_the_highest_priority_is_used() {
+        let dir = temp_dir(
+            "index-priority",
+            &[("README.md", "# Readme"), ("index.md", "# Index")],
+        );
+        let mut config = AutoGenConfig::new();
+        config.read_only = true;
+        config.directory_index_names = vec![String::from("index.md"), String::from("README.md")];
+
+        let (index_first, problems) = walk(&dir, &config);
+        config.directory_index_names.reverse();
+        let (readme_first, _) = walk(&dir, &config);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(index_first.path, Some(dir.join("index.md")));
+        assert!(index_first.children.is_empty());
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].kind == ProblemKind::ShadowedIndexFile);
+        assert_eq!(problems[0].path, dir.join("README.md"));
+        assert_eq!(readme_first.path, Some(dir.join("README.md")));
+    }
+
+    #[test]
+    fn shadowed_index_files_follow_the_duplicate_index_behavior() {
+        let dir = temp_dir(
+            "duplicate-index",
+            &[
+                ("README.md", "# Readme"),
+                ("index.md", "# Index"),
+                ("intro.md", "# Intro"),
+            ],
+        );
+        let mut config = AutoGenConfig::new();
+        config.read_only = true;
+        config.directory_index_names = vec![String::from("README.md"), String::from("index.md")];
+
+        config.duplicate_index_behavior = DuplicateIndexBehavior::Chapter;
+        let (chapter, chapter_problems) = walk(&dir, &config);
+        config.duplicate_index_behavior = DuplicateIndexBehavior::Drop;
+        let (dropped, dropped_problems) = walk(&dir, &config);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(chapter.path, Some(dir.join("README.md")));
+        let mut names = file_names(&chapter);
+        names.sort();
+        assert_eq!(names, vec!["index.md", "intro.md"]);
+        assert!(chapter_problems.is_empty());
+
+        assert_eq!(file_names(&dropped), vec!["intro.md"]);
+        assert_eq!(dropped_problems.len(), 1);
+        assert!(dropped_problems[0].kind == ProblemKind::ShadowedIndexFile);
+    }
+
+    #[cfg(unix)]
+    fn inode(path: &Path) -> u64 {
+        use std::os::unix::fs::MetadataExt;
+
+        fs::metadata(path).unwrap().ino()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn an_unchanged_summary_is_not_rewritten() {
+        let dir = temp_dir("unchanged-summary", &[("SUMMARY.md", "# Summary\n")]);
+        let summary_path = dir.join("SUMMARY.md");
+        let before = inode(&summary_path);
+
+        write_summary(&summary_path, "# Summary\n").unwrap();
+        let after = inode(&summary_path);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(before, after);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_changed_summary_is_replaced_by_a_new_file() {
+        let dir = temp_dir("changed-summary", &[("SUMMARY.md", "# Summary\n")]);
+        let summary_path = dir.join("SUMMARY.md");
+        let before = inode(&summary_path);
+
+        write_summary(&summary_path, "# Summary\n\n* [Intro](intro.md)\n").unwrap();
+        let after = inode(&summary_path);
+        let content = fs::read_to_string(&summary_path).unwrap();
+        let file_names: Vec<OsString> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // A new file is renamed over the old one, which is never truncated
+        assert_ne!(before, after);
+        assert_eq!(content, "# Summary\n\n* [Intro](intro.md)\n");
+        // and no temporary file is left
+        assert_eq!(file_names, vec![OsString::from("SUMMARY.md")]);
+    }
+
+    #[test]
+    fn atomic_writes_create_the_parent_directories() {
+        let dir = temp_dir("atomic-write", &[]);
+        let file_path = dir.join("generated").join("SUMMARY.md");
+
+        write_file_atomically(&file_path, "# Summary\n").unwrap();
+        let content = fs::read_to_string(&file_path);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(content.unwrap(), "# Summary\n");
+    }
 }
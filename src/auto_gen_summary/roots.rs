@@ -0,0 +1,237 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use mdbook::book::{Book, BookItem};
+
+use crate::auto_gen_summary::config::{AutoGenConfig, MountPosition};
+use crate::auto_gen_summary::lint::Problem;
+use crate::auto_gen_summary::metadata::MetadataCache;
+use crate::auto_gen_summary::{relative_path, walk_dir, MdEntry};
+
+/// Walks the `extra_roots` of the config and adds each of them to the
+/// children of `tree`, the root entry of `source_dir`, as a directory named
+/// after its mount name.
+///
+/// Config patterns and titles inside an extra root are relative to the
+/// extra root.
+pub fn add_extra_roots(
+    source_dir: &Path,
+    tree: &mut MdEntry,
+    config: &AutoGenConfig,
+    cache: &MetadataCache,
+    problems: &mut Vec<Problem>,
+) -> Result<(), Error> {
+    for extra_root in &config.extra_roots {
+        if !extra_root.path.is_dir() {
+            anyhow::bail!(
+                "The extra root '{}' is not a directory",
+                extra_root.path.display()
+            );
+        }
+
+        let mount_path = source_dir.join(&extra_root.mount);
+        if mount_path.exists()
+            || tree
+                .children
+                .iter()
+                .any(|child| child.sorting_path == mount_path)
+        {
+            anyhow::bail!(
+                "The extra root '{}' is mounted as '{}', which already exists in the source directory. Set its 'mount' to another name",
+                extra_root.path.display(),
+                extra_root.mount
+            );
+        }
+
+        let Some(mut entry) =
+            walk_dir(&extra_root.path, &extra_root.path, config, cache, problems)?
+        else {
+            eprintln!(
+                "Warn: The extra root '{}' is left out of the summary because it has no index file",
+                extra_root.path.display()
+            );
+            continue;
+        };
+
+        let dir_name = extra_root
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string());
+        if let Some(title) = &extra_root.title {
+            entry.title = title.clone();
+        } else if Some(&entry.title) == dir_name.as_ref() {
+            // The entry is named after its mount name, like the directories
+            // of the source directory
+            entry.title = extra_root.mount.clone();
+        }
+        entry.sorting_path = mount_path;
+        entry.is_dir = true;
+
+        tree.children.push(entry);
+    }
+
+    Ok(())
+}
+
+/// Moves the entries of the extra roots that are not sorted to the start or
+/// the end of the children of `tree`. Must be called after sorting.
+pub fn place_extra_roots(source_dir: &Path, tree: &mut MdEntry, config: &AutoGenConfig) {
+    for extra_root in &config.extra_roots {
+        if extra_root.position == MountPosition::Sorted {
+            continue;
+        }
+
+        let mount_path = source_dir.join(&extra_root.mount);
+        let Some(index) = tree
+            .children
+            .iter()
+            .position(|child| child.sorting_path == mount_path)
+        else {
+            continue;
+        };
+
+        let entry = tree.children.remove(index);
+        match extra_root.position {
+            MountPosition::Start => tree.children.insert(0, entry),
+            _ => tree.children.push(entry),
+        }
+    }
+}
+
+/// Returns the path a page is built at, given its path relative to
+/// `source_dir`. The pages of an extra root are linked with `..` in the
+/// summary and built under its mount name instead.
+pub fn mounted_path(source_dir: &Path, config: &AutoGenConfig, path: &Path) -> PathBuf {
+    for extra_root in &config.extra_roots {
+        let link = relative_path(source_dir, &extra_root.path);
+        if let Ok(rest) = path.strip_prefix(&link) {
+            return Path::new(&extra_root.mount).join(rest);
+        }
+    }
+
+    PathBuf::from(path)
+}
+
+/// Moves the chapters of the extra roots to their mounted path, so the
+/// renderers build them inside the book.
+pub fn mount_chapters(book: &mut Book, source_dir: &Path, config: &AutoGenConfig) {
+    if config.extra_roots.is_empty() {
+        return;
+    }
+
+    book.for_each_mut(|item| {
+        if let BookItem::Chapter(chapter) = item {
+            if let Some(path) = &chapter.path {
+                chapter.path = Some(mounted_path(source_dir, config, path));
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::str::FromStr;
+
+    use mdbook::Config;
+
+    use super::*;
+    use crate::auto_gen_summary::config::{ExtraRoot, MountPosition};
+    use crate::auto_gen_summary::render_summaries;
+
+    /// Creates a book with a src directory and an extra root in
+    /// `generated/api`, and returns its root
+    fn temp_book(name: &str) -> PathBuf {
+        let book_root =
+            std::env::temp_dir().join(format!("auto-gen-summary-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&book_root);
+        for (path, content) in [
+            ("src/README.md", "# Home\n"),
+            ("generated/api/README.md", "# API\n"),
+            ("generated/api/intro.md", "# Intro\n"),
+        ] {
+            let path = book_root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        book_root
+    }
+
+    /// Reads the config of the preprocessor from `book_toml`, without the
+    /// environment overrides
+    fn book_config(book_root: &Path, book_toml: &str) -> AutoGenConfig {
+        let mut config = AutoGenConfig::new();
+        config.book_root = PathBuf::from(book_root);
+        config
+            .apply_config(&Config::from_str(book_toml).unwrap())
+            .unwrap();
+        config
+    }
+
+    #[test]
+    fn extra_roots_are_linked_from_the_src_directory() {
+        let book_root = temp_book("extra-roots");
+        let config = book_config(
+            &book_root,
+            "[preprocessor.auto-gen-summary]\n\
+             extra-roots = [{ path = \"generated/api\", mount = \"api-reference\", title = \"API\" }]",
+        );
+
+        let summaries = render_summaries(&book_root.join("src"), &config);
+        fs::remove_dir_all(&book_root).unwrap();
+
+        let content = &summaries.unwrap()[0].content;
+        assert!(
+            content.contains(
+                "* [API](../generated/api/README.md)\n    * [intro.md](../generated/api/intro.md)"
+            ),
+            "{}",
+            content
+        );
+    }
+
+    // The extra roots of a renderer table used to be resolved against the
+    // current directory instead of the book root
+    #[test]
+    fn extra_roots_of_a_renderer_are_relative_to_the_book_root() {
+        let book_root = temp_book("renderer-extra-roots");
+        let config = book_config(
+            &book_root,
+            "[preprocessor.auto-gen-summary.renderer.epub]\n\
+             extra-roots = [{ path = \"generated/api\" }]",
+        );
+
+        let epub = config.for_renderer("epub").unwrap().unwrap();
+        let summaries = render_summaries(&book_root.join("src"), &epub);
+        fs::remove_dir_all(&book_root).unwrap();
+
+        assert_eq!(epub.extra_roots[0].path, book_root.join("generated/api"));
+        let content = &summaries.unwrap()[0].content;
+        assert!(
+            content.contains("* [api](../generated/api/README.md)"),
+            "{}",
+            content
+        );
+    }
+
+    #[test]
+    fn pages_of_extra_roots_are_built_under_their_mount_name() {
+        let mut config = AutoGenConfig::new();
+        config.extra_roots.push(ExtraRoot {
+            path: PathBuf::from("/book/generated/api"),
+            mount: String::from("api-reference"),
+            title: None,
+            position: MountPosition::Sorted,
+        });
+        let source_dir = Path::new("/book/src");
+
+        assert_eq!(
+            mounted_path(source_dir, &config, Path::new("../generated/api/intro.md")),
+            PathBuf::from("api-reference/intro.md")
+        );
+        assert_eq!(
+            mounted_path(source_dir, &config, Path::new("guide/intro.md")),
+            PathBuf::from("guide/intro.md")
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::auto_gen_summary::config::{SortGroup, SortKey};
+use crate::auto_gen_summary::MdEntry;
+
+/// Front matter key used by the `date` sort key
+const FRONT_MATTER_DATE: &str = "date";
+
+/// The first and last commit times of the files tracked by git
+type GitDates = HashMap<PathBuf, (SystemTime, SystemTime)>;
+
+/// The value an entry is compared by
+enum SortValue {
+    /// Only the tie-breaker (the entry's path) is compared
+    None,
+    Natural(String),
+    Text(String),
+    Time(Option<SystemTime>),
+}
+
+impl SortValue {
+    fn compare(&self, other: &SortValue) -> Ordering {
+        match (self, other) {
+            (SortValue::Natural(a), SortValue::Natural(b)) => natural_cmp(a, b),
+            (SortValue::Text(a), SortValue::Text(b)) => a.cmp(b),
+            (SortValue::Time(a), SortValue::Time(b)) => a.cmp(b),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+/// Recursively sorts the children of each entry by the entry's sort order.
+/// Children named in the entry's `order` are listed first, in that order.
+/// The other children are grouped into files and directories if the sort
+/// order has a group, and entries that compare equal are sorted by path.
+pub fn sort_entry_recursive(source_dir: &Path, entry: &mut MdEntry) {
+    let mut git_dates = None;
+    sort_entry_recursive_with(source_dir, entry, &mut git_dates);
+}
+
+fn sort_entry_recursive_with(
+    source_dir: &Path,
+    entry: &mut MdEntry,
+    git_dates: &mut Option<GitDates>,
+) {
+    let sort_order = entry.sort_order;
+
+    let mut keyed_children: Vec<(SortValue, MdEntry)> = entry
+        .children
+        .drain(..)
+        .map(|child| {
+            (
+                get_sort_value(source_dir, sort_order.key, &child, git_dates),
+                child,
+            )
+        })
+        .collect();
+
+    let order = &entry.order;
+    keyed_children.sort_by(|(a_value, a), (b_value, b)| {
+        let order_ordering = order_rank(order, a).cmp(&order_rank(order, b));
+        let group_ordering = group_rank(sort_order.group, a).cmp(&group_rank(sort_order.group, b));
+        let ordering = a_value
+            .compare(b_value)
+            .then_with(|| a.sorting_path.cmp(&b.sorting_path));
+
+        order_ordering
+            .then(group_ordering)
+            .then(if sort_order.reverse {
+                ordering.reverse()
+            } else {
+                ordering
+            })
+    });
+
+    entry.children = keyed_children.into_iter().map(|(_, child)| child).collect();
+
+    for child in &mut entry.children {
+        sort_entry_recursive_with(source_dir, child, git_dates);
+    }
+}
+
+/// The position of an entry's name in `order`. Entries that are not named
+/// are listed after the named ones.
+fn order_rank(order: &[String], entry: &MdEntry) -> usize {
+    // Draft chapters are named by their title, which may contain a `/`
+    let is_draft_chapter = entry.path.is_none() && !entry.is_dir;
+    let name = entry.sorting_path.file_name().unwrap_or_default();
+    order
+        .iter()
+        .position(|item| {
+            if is_draft_chapter {
+                entry.title == *item
+            } else {
+                name == item.as_str()
+            }
+        })
+        .unwrap_or(order.len())
+}
+
+/// The rank of the group an entry belongs to. Groups with a lower rank are
+/// listed first.
+fn group_rank(group: SortGroup, entry: &MdEntry) -> u8 {
+    match group {
+        SortGroup::Mixed => 0,
+        SortGroup::DirectoriesFirst => u8::from(!entry.is_dir),
+        SortGroup::FilesFirst => u8::from(entry.is_dir),
+    }
+}
+
+fn get_sort_value(
+    source_dir: &Path,
+    key: SortKey,
+    entry: &MdEntry,
+    git_dates: &mut Option<GitDates>,
+) -> SortValue {
+    match key {
+        SortKey::Path => SortValue::None,
+        SortKey::NaturalPath => {
+            SortValue::Natural(entry.sorting_path.to_string_lossy().to_string())
+        }
+        SortKey::Title => SortValue::Text(entry.title.to_lowercase()),
+        SortKey::ModifiedTime => {
+            let path = entry.path.as_ref().unwrap_or(&entry.sorting_path);
+            SortValue::Time(fs::metadata(path).and_then(|m| m.modified()).ok())
+        }
+        SortKey::Date => match entry.front_matter.get(FRONT_MATTER_DATE) {
+            Some(date) => SortValue::Text(date.clone()),
+            None => SortValue::Text(String::new()),
+        },
+        SortKey::GitFirstCommit | SortKey::GitLastCommit => {
+            let git_dates = git_dates.get_or_insert_with(|| load_git_dates(source_dir));
+            let dates = entry.path.as_ref().and_then(|path| git_dates.get(path));
+
+            SortValue::Time(dates.map(|(first, last)| {
+                if key == SortKey::GitFirstCommit {
+                    *first
+                } else {
+                    *last
+                }
+            }))
+        }
+    }
+}
+
+/// Reads the first and last commit times of every file in `source_dir` from
+/// the git history of the repository containing it.
+fn load_git_dates(source_dir: &Path) -> GitDates {
+    let mut git_dates = HashMap::new();
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(source_dir)
+        .args([
+            "-c",
+            "core.quotePath=false",
+            "log",
+            "--format=%x00%ct",
+            "--name-only",
+            "--no-renames",
+            "--relative",
+        ])
+        .output();
+
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        _ => {
+            eprintln!(
+                "Warn: Failed to read the git history of '{}'. Entries will be sorted by path.",
+                source_dir.to_string_lossy()
+            );
+            return git_dates;
+        }
+    };
+
+    // Commits are listed newest first
+    let mut commit_time = None;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some(timestamp) = line.strip_prefix('\0') {
+            commit_time = timestamp
+                .parse::<u64>()
+                .ok()
+                .map(|t| UNIX_EPOCH + Duration::from_secs(t));
+            continue;
+        }
+
+        let Some(commit_time) = commit_time else {
+            continue;
+        };
+        if line.is_empty() {
+            continue;
+        }
+
+        git_dates
+            .entry(source_dir.join(line))
+            .and_modify(|(first, _)| *first = commit_time)
+            .or_insert((commit_time, commit_time));
+    }
+
+    git_dates
+}
+
+/// Compares strings so that runs of digits are compared by their numeric
+/// value. For example, "chapter-2" sorts before "chapter-10".
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_char), Some(b_char)) if a_char.is_ascii_digit() && b_char.is_ascii_digit() => {
+                let a_number = take_digits(&mut a_chars);
+                let b_number = take_digits(&mut b_chars);
+                let a_trimmed = a_number.trim_start_matches('0');
+                let b_trimmed = b_number.trim_start_matches('0');
+
+                let ordering = a_trimmed
+                    .len()
+                    .cmp(&b_trimmed.len())
+                    .then_with(|| a_trimmed.cmp(b_trimmed))
+                    .then_with(|| a_number.len().cmp(&b_number.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(a_char), Some(b_char)) => {
+                let ordering = a_char.cmp(b_char);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+        digits.push(*c);
+        chars.next();
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_cmp_compares_numbers_by_value() {
+        assert_eq!(natural_cmp("chapter-2", "chapter-10"), Ordering::Less);
+        assert_eq!(natural_cmp("chapter-10", "chapter-9"), Ordering::Greater);
+        assert_eq!(natural_cmp("v1.10", "v1.9"), Ordering::Greater);
+        assert_eq!(natural_cmp("007", "7"), Ordering::Greater);
+        assert_eq!(natural_cmp("chapter", "chapter-1"), Ordering::Less);
+        assert_eq!(natural_cmp("a2b", "a2b"), Ordering::Equal);
+        assert_eq!(natural_cmp("B", "a"), Ordering::Less);
+    }
+}
@@ -0,0 +1,300 @@
+use std::path::{Path, PathBuf};
+
+use crate::auto_gen_summary::hidden::{self, HiddenPage};
+use crate::auto_gen_summary::{relative_path, MdEntry};
+
+/// Hoists the children of the directories listed in `directories` into
+/// their parent directory.
+///
+/// `directories` are paths relative to `root_dir`. The index file of a
+/// flattened directory is kept as a regular entry in the parent directory.
+pub fn flatten_directories(root_dir: &Path, entry: &mut MdEntry, directories: &[String]) {
+    let directories: Vec<PathBuf> = directories
+        .iter()
+        .map(|d| PathBuf::from(d.trim_matches('/')))
+        .collect();
+
+    flatten_directories_recursive(root_dir, entry, &directories);
+}
+
+fn flatten_directories_recursive(root_dir: &Path, entry: &mut MdEntry, directories: &[PathBuf]) {
+    let mut children = Vec::with_capacity(entry.children.len());
+
+    for mut child in entry.children.drain(..) {
+        flatten_directories_recursive(root_dir, &mut child, directories);
+
+        let should_flatten = child.is_dir
+            && child
+                .sorting_path
+                .strip_prefix(root_dir)
+                .map(|p| directories.iter().any(|d| d == p))
+                .unwrap_or(false);
+
+        if !should_flatten {
+            children.push(child);
+            continue;
+        }
+
+        let hoisted_children = std::mem::take(&mut child.children);
+
+        if child.path.is_some() {
+            child.is_dir = false;
+            children.push(child);
+        }
+
+        children.extend(hoisted_children);
+    }
+
+    entry.children = children;
+}
+
+/// Removes the draft entries. The children of a draft directory take its
+/// place in the parent directory.
+pub fn omit_drafts(entry: &mut MdEntry) {
+    let mut children = Vec::with_capacity(entry.children.len());
+
+    for mut child in entry.children.drain(..) {
+        omit_drafts(&mut child);
+
+        if child.path.is_some() {
+            children.push(child);
+        } else {
+            children.extend(child.children);
+        }
+    }
+
+    entry.children = children;
+}
+
+/// Merges chains of directories that contain nothing but their index file
+/// and a single subdirectory into one entry.
+///
+/// The merged entry's title is the directory titles joined with `/`, and it
+/// links to the last index file in the chain. The index files of the other
+/// directories are no longer listed, so they are added to `pages` to be
+/// built as hidden pages.
+pub fn collapse_single_child_directories(
+    root_dir: &Path,
+    entry: &mut MdEntry,
+    pages: &mut Vec<HiddenPage>,
+) {
+    // The title of the directory whose index file the entry links to
+    let mut index_title = entry.title.clone();
+
+    while entry.is_dir && entry.children.len() == 1 && entry.children[0].is_dir {
+        let child = entry.children.pop().unwrap();
+
+        if child.path.is_some() {
+            if let Some(path) = &entry.path {
+                pages.push(HiddenPage {
+                    title: index_title,
+                    path: relative_path(root_dir, path),
+                });
+            }
+            index_title = child.title.clone();
+            entry.path = child.path;
+        }
+        entry.title = format!("{}/{}", entry.title, child.title);
+        entry.children = child.children;
+    }
+
+    for child in &mut entry.children {
+        collapse_single_child_directories(root_dir, child, pages);
+    }
+}
+
+/// Removes the children of `entry` that are deeper than `max_depth`, and
+/// adds their pages to `pages` to be built as hidden pages. `depth` is the
+/// depth of `entry`, starting at `0` for the top-level entries.
+pub fn hide_deep_entries(
+    root_dir: &Path,
+    entry: &mut MdEntry,
+    depth: usize,
+    max_depth: usize,
+    pages: &mut Vec<HiddenPage>,
+) {
+    if depth + 1 >= max_depth {
+        for child in entry.children.drain(..) {
+            hidden::collect_pages(root_dir, &child, pages);
+        }
+        return;
+    }
+
+    for child in &mut entry.children {
+        hide_deep_entries(root_dir, child, depth + 1, max_depth, pages);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::auto_gen_summary::config::AutoGenConfig;
+
+    fn dir(title: &str, index: Option<&str>, children: Vec<MdEntry>) -> MdEntry {
+        MdEntry {
+            title: String::from(title),
+            path: index.map(PathBuf::from),
+            url: None,
+            sorting_path: PathBuf::from(title),
+            is_dir: true,
+            front_matter: BTreeMap::new(),
+            sort_order: AutoGenConfig::new().sort_order,
+            order: Vec::new(),
+            hidden: false,
+            children,
+        }
+    }
+
+    fn file(title: &str) -> MdEntry {
+        MdEntry {
+            is_dir: false,
+            ..dir(title, Some(&format!("{}.md", title)), vec![])
+        }
+    }
+
+    fn titles(entry: &MdEntry) -> Vec<&str> {
+        entry
+            .children
+            .iter()
+            .map(|child| child.title.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn flatten_hoists_children_and_keeps_the_index() {
+        let mut root = dir(
+            "",
+            None,
+            vec![
+                dir("api", Some("api.md"), vec![file("alpha"), file("beta")]),
+                dir("guide", None, vec![file("basics")]),
+                file("intro"),
+            ],
+        );
+
+        flatten_directories(
+            Path::new(""),
+            &mut root,
+            &[String::from("api/"), String::from("guide")],
+        );
+
+        assert_eq!(
+            titles(&root),
+            vec!["api", "alpha", "beta", "basics", "intro"]
+        );
+        assert!(!root.children[0].is_dir);
+        assert!(root.children[0].children.is_empty());
+    }
+
+    #[test]
+    fn omit_drafts_moves_children_of_draft_directories_up() {
+        let mut root = dir(
+            "",
+            None,
+            vec![
+                dir(
+                    "guide",
+                    None,
+                    vec![file("basics"), dir("todo", None, vec![])],
+                ),
+                file("intro"),
+                MdEntry {
+                    is_dir: false,
+                    ..dir("later", None, vec![])
+                },
+            ],
+        );
+
+        omit_drafts(&mut root);
+
+        assert_eq!(titles(&root), vec!["basics", "intro"]);
+    }
+
+    #[test]
+    fn hide_deep_entries_keeps_their_pages() {
+        let mut guide = dir(
+            "guide",
+            Some("guide.md"),
+            vec![
+                file("intro"),
+                dir("advanced", Some("advanced.md"), vec![file("macros")]),
+            ],
+        );
+
+        let mut pages = Vec::new();
+        hide_deep_entries(Path::new(""), &mut guide, 0, 2, &mut pages);
+
+        assert_eq!(titles(&guide), vec!["intro", "advanced"]);
+        assert!(guide.children[1].children.is_empty());
+        let hidden: Vec<&Path> = pages.iter().map(|page| page.path.as_path()).collect();
+        assert_eq!(hidden, vec![Path::new("macros.md")]);
+    }
+
+    #[test]
+    fn collapse_merges_directories_without_index() {
+        let mut reference = dir(
+            "reference",
+            None,
+            vec![dir("v1", None, vec![dir("http", Some("http.md"), vec![])])],
+        );
+
+        let mut pages = Vec::new();
+        collapse_single_child_directories(Path::new(""), &mut reference, &mut pages);
+
+        assert_eq!(reference.title, "reference/v1/http");
+        assert_eq!(reference.path, Some(PathBuf::from("http.md")));
+        assert!(reference.children.is_empty());
+        assert!(pages.is_empty());
+    }
+
+    #[test]
+    fn collapse_hides_the_index_files_of_merged_directories() {
+        let mut reference = dir(
+            "reference",
+            Some("reference.md"),
+            vec![dir(
+                "v1",
+                Some("v1.md"),
+                vec![dir("http", Some("http.md"), vec![file("get")])],
+            )],
+        );
+
+        let mut pages = Vec::new();
+        collapse_single_child_directories(Path::new(""), &mut reference, &mut pages);
+
+        assert_eq!(reference.title, "reference/v1/http");
+        assert_eq!(reference.path, Some(PathBuf::from("http.md")));
+        assert_eq!(titles(&reference), vec!["get"]);
+        let hidden: Vec<(&str, &Path)> = pages
+            .iter()
+            .map(|page| (page.title.as_str(), page.path.as_path()))
+            .collect();
+        assert_eq!(
+            hidden,
+            vec![
+                ("reference", Path::new("reference.md")),
+                ("v1", Path::new("v1.md"))
+            ]
+        );
+    }
+
+    #[test]
+    fn collapse_keeps_the_last_index_file() {
+        let mut guide = dir(
+            "guide",
+            Some("guide.md"),
+            vec![dir("basics", None, vec![file("intro"), file("setup")])],
+        );
+
+        let mut pages = Vec::new();
+        collapse_single_child_directories(Path::new(""), &mut guide, &mut pages);
+
+        assert_eq!(guide.title, "guide/basics");
+        assert_eq!(guide.path, Some(PathBuf::from("guide.md")));
+        assert_eq!(titles(&guide), vec!["intro", "setup"]);
+        assert!(pages.is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use anyhow::{Context, Error};
+use mdbook::book::{parse_summary, SummaryItem};
+
+use crate::auto_gen_summary::RelativizedLink;
+
+/// A chapter of the summary: what a summary line is expected to parse to
+#[derive(PartialEq)]
+pub struct SummaryChapter {
+    depth: usize,
+    title: String,
+    /// None for drafts
+    location: Option<PathBuf>,
+}
+
+impl SummaryChapter {
+    pub fn new(depth: usize, title: &str, link: &RelativizedLink) -> SummaryChapter {
+        SummaryChapter {
+            depth,
+            title: String::from(title),
+            location: if link.0.is_empty() {
+                None
+            } else {
+                Some(PathBuf::from(&link.0))
+            },
+        }
+    }
+}
+
+impl fmt::Display for SummaryChapter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'[{}]({})' at depth {}",
+            self.title,
+            self.location
+                .as_ref()
+                .map(|location| location.display().to_string())
+                .unwrap_or_default(),
+            self.depth
+        )
+    }
+}
+
+/// Parses the generated summary with mdbook's parser and checks that it
+/// reads the same chapters as the ones it was generated from.
+pub fn verify_summary(summary: &str, expected: &[SummaryChapter]) -> Result<(), Error> {
+    let parsed = parse_summary(summary).context("mdbook failed to parse the generated summary")?;
+
+    let mut found = Vec::new();
+    for items in [
+        &parsed.prefix_chapters,
+        &parsed.numbered_chapters,
+        &parsed.suffix_chapters,
+    ] {
+        collect_chapters(items, 0, &mut found)?;
+    }
+
+    for (i, (expected, found)) in expected.iter().zip(&found).enumerate() {
+        if expected != found {
+            anyhow::bail!(
+                "The generated summary doesn't parse as expected: chapter {} should be {}, \
+                 but mdbook read {}. Paths with markdown syntax (such as spaces or \
+                 parentheses) may need renaming, and other titles a 'titles' override",
+                i + 1,
+                expected,
+                found
+            );
+        }
+    }
+
+    if expected.len() != found.len() {
+        anyhow::bail!(
+            "The generated summary doesn't parse as expected: it should have {} chapters, \
+             but mdbook read {}",
+            expected.len(),
+            found.len()
+        );
+    }
+
+    Ok(())
+}
+
+fn collect_chapters(
+    items: &[SummaryItem],
+    depth: usize,
+    chapters: &mut Vec<SummaryChapter>,
+) -> Result<(), Error> {
+    for item in items {
+        match item {
+            SummaryItem::Link(link) => {
+                chapters.push(SummaryChapter {
+                    depth,
+                    title: link.name.clone(),
+                    location: link.location.clone(),
+                });
+                collect_chapters(&link.nested_items, depth + 1, chapters)?;
+            }
+            SummaryItem::Separator => {}
+            SummaryItem::PartTitle(title) => anyhow::bail!(
+                "The generated summary doesn't parse as expected: mdbook read part title '{}'",
+                title
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto_gen_summary::generate_summary_line;
+
+    fn link(path: &str) -> RelativizedLink {
+        RelativizedLink(String::from(path))
+    }
+
+    #[test]
+    fn accepts_a_summary_that_parses_to_its_chapters() {
+        let summary =
+            "# Summary\n\n- [Intro](intro.md)\n    - [Later]()\n- [Guide](guide/README.md)\n";
+        let expected = [
+            SummaryChapter::new(0, "Intro", &link("intro.md")),
+            SummaryChapter::new(1, "Later", &link("")),
+            SummaryChapter::new(0, "Guide", &link("guide/README.md")),
+        ];
+
+        verify_summary(summary, &expected).unwrap();
+    }
+
+    #[test]
+    fn rejects_titles_with_markdown_syntax() {
+        let summary = "# Summary\n\n- [*Intro*](intro.md)\n";
+        let expected = [SummaryChapter::new(0, "*Intro*", &link("intro.md"))];
+
+        let error = verify_summary(summary, &expected).unwrap_err();
+        assert!(error.to_string().contains("chapter 1"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_missing_chapters() {
+        let summary = "# Summary\n\n- [Intro](intro.md)\n";
+        let expected = [
+            SummaryChapter::new(0, "Intro", &link("intro.md")),
+            SummaryChapter::new(0, "Guide", &link("guide.md")),
+        ];
+
+        assert!(verify_summary(summary, &expected).is_err());
+    }
+
+    #[test]
+    fn generated_lines_read_back_as_their_titles() {
+        let titles = [
+            "Using `Config`",
+            "Vec<T> basics",
+            "[Draft] *notes* and __init__",
+            "AT&amp;T",
+            "C:\\path\\",
+        ];
+
+        let mut summary = String::from("# Summary\n\n");
+        let mut expected = Vec::new();
+        for (i, title) in titles.iter().enumerate() {
+            let link = link(&format!("chapter-{}.md", i));
+            summary.push_str(&generate_summary_line(0, title, &link));
+            summary.push('\n');
+            expected.push(SummaryChapter::new(0, title, &link));
+        }
+
+        verify_summary(&summary, &expected).unwrap();
+    }
+}
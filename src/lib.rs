@@ -0,0 +1 @@
+pub mod auto_gen_summary;
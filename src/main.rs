@@ -1,15 +1,154 @@
-mod auto_gen_summary;
-
-use auto_gen_summary::AutoGenSummary;
+use anyhow::Context;
 use clap::{App, Arg, ArgMatches, SubCommand};
 use mdbook::errors::Error;
 use mdbook::preprocess::{CmdPreprocessor, Preprocessor};
-use std::collections::HashSet;
+use mdbook::Config;
+use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::str::FromStr;
+
+use mdbook_auto_gen_summary::auto_gen_summary::config::{
+    self, AutoGenConfig, DirectoryWithoutIndexBehavior, DuplicateIndexBehavior, MaxDepthBehavior,
+    MissingTranslationBehavior, SortGroup, SortKey,
+};
+use mdbook_auto_gen_summary::auto_gen_summary::export::ExportFormat;
+use mdbook_auto_gen_summary::auto_gen_summary::{self, language, lint, migrate, AutoGenSummary};
 
-use crate::auto_gen_summary::config::{AutoGenConfig, DirectoryWithoutIndexBehavior};
+/// Arguments that set the options of `AutoGenConfig`, shared by the
+/// subcommands that walk the source directory.
+fn config_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("title")
+            .required(false)
+            .short("t")
+            .long("title")
+            .help("Use the first line of markdown files the title in SUMMARY.md"),
+        Arg::with_name("no-title")
+            .required(false)
+            .long("no-title")
+            .conflicts_with("title")
+            .help("Use the file names as the titles in SUMMARY.md"),
+        Arg::with_name("dir-title")
+            .required(false)
+            .short("T")
+            .long("dir-title")
+            .help("Use the first line of directory index files the title in SUMMARY.md"),
+        Arg::with_name("no-dir-title")
+            .required(false)
+            .long("no-dir-title")
+            .conflicts_with("dir-title")
+            .help("Use the directory names as the titles of directories in SUMMARY.md"),
+        Arg::with_name("dir-index-names")
+            .required(false)
+            .short("i")
+            .long("dir-index-names")
+            .takes_value(true)
+            .use_delimiter(true)
+            .help("Name of files to use as a directory index, in order of priority"),
+        Arg::with_name("duplicate-index-behavior")
+            .required(false)
+            .short("x")
+            .long("duplicate-index-behavior")
+            .takes_value(true)
+            .case_insensitive(true)
+            .possible_values(DuplicateIndexBehavior::VALUES)
+            .help("Behavior of index files not used because a higher priority index file exists"),
+        Arg::with_name("dir-without-index-behavior")
+            .required(false)
+            .short("w")
+            .long("dir-without-index-behavior")
+            .takes_value(true)
+            .case_insensitive(true)
+            .possible_values(DirectoryWithoutIndexBehavior::VALUES)
+            .help("Behavior of a directory without an index file"),
+        Arg::with_name("reserved-files")
+            .required(false)
+            .short("R")
+            .long("reserved-files")
+            .takes_value(true)
+            .use_delimiter(true)
+            .help("Patterns of files that are never listed in SUMMARY.md"),
+        Arg::with_name("collapse-single-child-dirs")
+            .required(false)
+            .short("c")
+            .long("collapse-single-child-dirs")
+            .help("Merge chains of directories with a single subdirectory into one entry"),
+        Arg::with_name("no-collapse-single-child-dirs")
+            .required(false)
+            .long("no-collapse-single-child-dirs")
+            .conflicts_with("collapse-single-child-dirs")
+            .help("List every directory of a chain of single subdirectories as its own entry"),
+        Arg::with_name("flatten-dirs")
+            .required(false)
+            .short("f")
+            .long("flatten-dirs")
+            .takes_value(true)
+            .use_delimiter(true)
+            .help("Directories whose children are hoisted into the parent directory"),
+        Arg::with_name("max-depth")
+            .required(false)
+            .short("d")
+            .long("max-depth")
+            .takes_value(true)
+            .help("Maximum number of nesting levels shown in SUMMARY.md"),
+        Arg::with_name("max-depth-behavior")
+            .required(false)
+            .short("D")
+            .long("max-depth-behavior")
+            .takes_value(true)
+            .case_insensitive(true)
+            .possible_values(MaxDepthBehavior::VALUES)
+            .help("Behavior of entries deeper than the maximum depth"),
+        Arg::with_name("sort-by")
+            .required(false)
+            .short("s")
+            .long("sort-by")
+            .takes_value(true)
+            .case_insensitive(true)
+            .possible_values(SortKey::VALUES)
+            .help("Value the entries in a directory are sorted by"),
+        Arg::with_name("sort-reverse")
+            .required(false)
+            .short("r")
+            .long("sort-reverse")
+            .help("Reverse the sort order"),
+        Arg::with_name("no-sort-reverse")
+            .required(false)
+            .long("no-sort-reverse")
+            .conflicts_with("sort-reverse")
+            .help("Don't reverse the sort order"),
+        Arg::with_name("sort-group")
+            .required(false)
+            .short("g")
+            .long("sort-group")
+            .takes_value(true)
+            .case_insensitive(true)
+            .possible_values(SortGroup::VALUES)
+            .help("Whether files and directories are sorted as separate groups"),
+        Arg::with_name("languages")
+            .required(false)
+            .short("l")
+            .long("languages")
+            .takes_value(true)
+            .use_delimiter(true)
+            .help("Language subdirectories that each get their own SUMMARY.md"),
+        Arg::with_name("missing-translation-behavior")
+            .required(false)
+            .short("m")
+            .long("missing-translation-behavior")
+            .takes_value(true)
+            .case_insensitive(true)
+            .possible_values(MissingTranslationBehavior::VALUES)
+            .help("Behavior of pages missing in a language"),
+        Arg::with_name("cache-file")
+            .required(false)
+            .long("cache-file")
+            .takes_value(true)
+            .help("File to cache the titles and front matter of markdown files in"),
+    ]
+}
 
 pub fn make_app() -> App<'static, 'static> {
     App::new("auto-gen-summary-preprocessor")
@@ -21,47 +160,106 @@ pub fn make_app() -> App<'static, 'static> {
         )
         .subcommand(
             SubCommand::with_name("gen")
+                .arg(Arg::with_name("dir").required(true).help(
+                    "A path to the book root or src directory. The options of the book are \
+                     read from the closest book.toml in this directory or above",
+                ))
+                .args(&config_args())
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true)
+                        .help("Write the summary to this file instead of SUMMARY.md in the src directory"),
+                )
+                .arg(
+                    Arg::with_name("check-translations")
+                        .long("check-translations")
+                        .help(
+                            "Also list the pages missing in each of the languages, and exit with \
+                             status 1 if any page is missing",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(ExportFormat::VALUES)
+                        .help("Also print the table of contents to stdout in this format"),
+                )
+                .about("gen SUMMARY.md"),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
                 .arg(
                     Arg::with_name("dir")
-                        .required(true)
-                        .help("A path to the mdbook src directory"),
+                        .default_value(".")
+                        .help("A path to the book root or src directory"),
                 )
+                .args(&config_args())
                 .arg(
-                    Arg::with_name("title")
-                        .required(false)
-                        .short("t")
-                        .long("title")
-                        .help("Use the first line of markdown files the title in SUMMARY.md"),
+                    Arg::with_name("schema")
+                        .long("schema")
+                        .help("Print the JSON Schema of the preprocessor's table in book.toml instead"),
                 )
+                .about(
+                    "Print the options gen uses for the book, after merging the defaults, \
+                     book.toml and the given flags, as TOML",
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("compare")
                 .arg(
-                    Arg::with_name("dir-title")
-                        .required(false)
-                        .short("T")
-                        .long("dir-title")
+                    Arg::with_name("dirs")
+                        .required(true)
+                        .multiple(true)
+                        .min_values(2)
                         .help(
-                            "Use the first line of directory index files the title in SUMMARY.md",
+                            "Paths to the language root directories to compare. The options of \
+                             the book are read from the closest book.toml above the first one",
                         ),
                 )
+                .args(&config_args())
+                .about(
+                    "Compare language root directories and list the pages missing in each. \
+                     Exits with status 1 if any page is missing",
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("migrate")
                 .arg(
-                    Arg::with_name("dir-index-names")
-                        .required(false)
-                        .short("i")
-                        .long("dir-index-names")
-                        .takes_value(true)
-                        .use_delimiter(true)
-                        .help("Name of files to use as a directory index"),
+                    Arg::with_name("dir").required(true).help(
+                        "A path to the book root or src directory. The options of the book are \
+                         read from the closest book.toml in this directory or above",
+                    ),
+                )
+                .args(&config_args())
+                .about(
+                    "Write the order, titles and drafts of the existing SUMMARY.md to \
+                     directory config files, so gen reproduces the same structure",
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("lint")
+                .arg(
+                    Arg::with_name("dir").required(true).help(
+                        "A path to the book root or src directory. The options of the book are \
+                         read from the closest book.toml in this directory or above",
+                    ),
                 )
+                .args(&config_args())
                 .arg(
-                    Arg::with_name("dir-without-index-behavior")
-                        .required(false)
-                        .short("w")
-                        .long("dir-without-index-behavior")
+                    Arg::with_name("format")
+                        .long("format")
                         .takes_value(true)
-                        .case_insensitive(true)
-                        .possible_values(&["ignore", "draft", "generate-stub-index"])
-                        .help("Behavior of a directory without an index file"),
+                        .possible_values(&["human", "json"])
+                        .default_value("human")
+                        .help("Output format of the problems"),
                 )
-                .about("gen SUMMARY.md"),
+                .about(
+                    "Report problems with the structure of the book without writing anything. \
+                     Exits with status 1 if any problem is found",
+                ),
         )
 }
 
@@ -73,48 +271,194 @@ fn main() {
     if let Some(sub_args) = matches.subcommand_matches("supports") {
         handle_supports(&preprocessor, sub_args);
     } else if let Some(sub_args) = matches.subcommand_matches("gen") {
-        let source_dir = sub_args
-            .value_of("dir")
-            .expect("Required argument")
-            .to_string();
+        let dir = PathBuf::from(sub_args.value_of("dir").expect("Required argument"));
 
-        let mut config = AutoGenConfig::new();
-        config.first_line_as_link_text = sub_args.is_present("title");
-        config.index_first_line_as_directory_link_text = sub_args.is_present("dir-title");
+        let (mut config, source_dir) = match load_book_config(&dir) {
+            Ok(book_config) => book_config,
+            Err(e) => {
+                eprintln!("{:#}", e);
+                process::exit(1);
+            }
+        };
+        apply_args(&mut config, sub_args);
 
-        if let Some(behavior) = sub_args.value_of("dir-without-index-behavior") {
-            config.directory_without_index_behavior =
-                DirectoryWithoutIndexBehavior::from_str(behavior).unwrap();
+        if let Some(output) = sub_args.value_of("output") {
+            config.output = Some(PathBuf::from(output));
         }
 
-        if let Some(index_names) = sub_args.values_of("dir-index-names") {
-            let mut directory_index_names = HashSet::new();
-            let mut generated_directory_index_name = None;
-
-            for item in index_names {
-                directory_index_names.insert(String::from(item));
+        let tocs = match auto_gen_summary::gen_summary(&source_dir, &config) {
+            Ok(tocs) => tocs,
+            Err(e) => {
+                eprintln!("{:#}", e);
+                process::exit(1);
+            }
+        };
 
-                if generated_directory_index_name.is_none() {
-                    generated_directory_index_name = Some(String::from(item));
+        if let Some(format) = enum_value::<ExportFormat>(sub_args, "format") {
+            match format.serialize(&tocs) {
+                Ok(content) => println!("{}", content.trim_end()),
+                Err(e) => {
+                    eprintln!("{:#}", e);
+                    process::exit(1);
                 }
             }
-
-            let Some(generated_directory_index_name) = generated_directory_index_name else {
-                eprintln!("Directory index names must not be empty.");
-                process::exit(1);
-            };
-
-            config.generated_directory_index_name = generated_directory_index_name;
-            config.directory_index_names = directory_index_names;
         }
 
-        auto_gen_summary::gen_summary(&PathBuf::from(source_dir), &config);
+        if sub_args.is_present("check-translations") {
+            check_translations(&source_dir, &config);
+        }
+    } else if let Some(sub_args) = matches.subcommand_matches("config") {
+        handle_config(sub_args);
+    } else if let Some(sub_args) = matches.subcommand_matches("compare") {
+        handle_compare(sub_args);
+    } else if let Some(sub_args) = matches.subcommand_matches("migrate") {
+        handle_migrate(sub_args);
+    } else if let Some(sub_args) = matches.subcommand_matches("lint") {
+        handle_lint(sub_args);
     } else if let Err(e) = handle_preprocessing(&preprocessor) {
         eprintln!("{}", e);
         process::exit(1);
     }
 }
 
+/// Loads the config of the book that `dir` belongs to, and returns it with
+/// the book's src directory. If no `book.toml` is found, returns the default
+/// config with `dir` as the src directory. Environment variable overrides
+/// are applied in both cases.
+fn load_book_config(dir: &Path) -> Result<(AutoGenConfig, PathBuf), Error> {
+    let Some(book_root) = config::find_book_root(dir) else {
+        // The name of the directory is used as its title, so `.` must be
+        // resolved
+        let source_dir = fs::canonicalize(dir)
+            .with_context(|| format!("Failed to find the directory '{}'", dir.display()))?;
+        let mut config = AutoGenConfig::new();
+        config.apply_env()?;
+        return Ok((config, source_dir));
+    };
+
+    let config_file = book_root.join("book.toml");
+    let mut mdbook_config = Config::from_disk(&config_file)
+        .with_context(|| format!("Failed to read '{}'", config_file.display()))?;
+    mdbook_config.update_from_env();
+
+    let config = AutoGenConfig::from_book(&book_root, &mdbook_config)?;
+
+    Ok((config, book_root.join(&mdbook_config.book.src)))
+}
+
+/// Sets the options of `config` given in the arguments of a subcommand.
+/// Options that are not given are left unchanged.
+fn apply_args(config: &mut AutoGenConfig, sub_args: &ArgMatches) {
+    if let Some(v) = flag_value(sub_args, "title") {
+        config.first_line_as_link_text = v;
+    }
+    if let Some(v) = flag_value(sub_args, "dir-title") {
+        config.index_first_line_as_directory_link_text = v;
+    }
+    if let Some(v) = flag_value(sub_args, "collapse-single-child-dirs") {
+        config.collapse_single_child_directories = v;
+    }
+
+    if let Some(reserved_files) = sub_args.values_of("reserved-files") {
+        config.reserved_files = reserved_files.map(String::from).collect();
+    }
+
+    if let Some(flatten_dirs) = sub_args.values_of("flatten-dirs") {
+        config.flatten_directories = flatten_dirs.map(String::from).collect();
+    }
+
+    if let Some(behavior) = enum_value(sub_args, "dir-without-index-behavior") {
+        config.directory_without_index_behavior = behavior;
+    }
+
+    if let Some(behavior) = enum_value(sub_args, "duplicate-index-behavior") {
+        config.duplicate_index_behavior = behavior;
+    }
+
+    if let Some(max_depth) = sub_args.value_of("max-depth") {
+        match max_depth.parse::<usize>() {
+            Ok(max_depth) if max_depth >= 1 => config.max_depth = Some(max_depth),
+            _ => {
+                eprintln!("Max depth must be an integer of at least 1.");
+                process::exit(1);
+            }
+        }
+    }
+
+    if let Some(behavior) = enum_value(sub_args, "max-depth-behavior") {
+        config.max_depth_behavior = behavior;
+    }
+
+    if let Some(sort_by) = enum_value(sub_args, "sort-by") {
+        config.sort_order.key = sort_by;
+    }
+    if let Some(v) = flag_value(sub_args, "sort-reverse") {
+        config.sort_order.reverse = v;
+    }
+
+    if let Some(sort_group) = enum_value(sub_args, "sort-group") {
+        config.sort_order.group = sort_group;
+    }
+
+    if let Some(languages) = sub_args.values_of("languages") {
+        config.languages = languages.map(String::from).collect();
+    }
+
+    if let Some(behavior) = enum_value(sub_args, "missing-translation-behavior") {
+        config.missing_translation_behavior = behavior;
+    }
+
+    if let Some(cache_file) = sub_args.value_of("cache-file") {
+        config.cache_file = Some(PathBuf::from(cache_file));
+    }
+
+    if let Some(index_names) = sub_args.values_of("dir-index-names") {
+        let mut directory_index_names = Vec::new();
+        let mut generated_directory_index_name = None;
+
+        for item in index_names {
+            if !directory_index_names.iter().any(|name| name == item) {
+                directory_index_names.push(String::from(item));
+            }
+
+            if generated_directory_index_name.is_none() {
+                generated_directory_index_name = Some(String::from(item));
+            }
+        }
+
+        let Some(generated_directory_index_name) = generated_directory_index_name else {
+            eprintln!("Directory index names must not be empty.");
+            process::exit(1);
+        };
+
+        config.generated_directory_index_name = generated_directory_index_name;
+        config.directory_index_names = directory_index_names;
+    }
+}
+
+/// Returns the value of a boolean option set by the flag `name` or by its
+/// `no-` counterpart, or `None` if neither is given.
+fn flag_value(sub_args: &ArgMatches, name: &str) -> Option<bool> {
+    if sub_args.is_present(name) {
+        Some(true)
+    } else if sub_args.is_present(format!("no-{}", name)) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Returns the value of an argument that takes one of the values of an
+/// option enum. The value is matched case-insensitively.
+fn enum_value<T: FromStr>(sub_args: &ArgMatches, name: &str) -> Option<T> {
+    let value = sub_args.value_of(name)?.to_lowercase();
+    Some(
+        value
+            .parse()
+            .unwrap_or_else(|_| unreachable!("The value is one of the possible values")),
+    )
+}
+
 fn handle_preprocessing(pre: &dyn Preprocessor) -> Result<(), Error> {
     let (ctx, book) = CmdPreprocessor::parse_input(io::stdin())?;
 
@@ -134,9 +478,190 @@ fn handle_preprocessing(pre: &dyn Preprocessor) -> Result<(), Error> {
     Ok(())
 }
 
+fn handle_config(sub_args: &ArgMatches) {
+    if sub_args.is_present("schema") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&config::json_schema())
+                .expect("The schema is serializable")
+        );
+        return;
+    }
+
+    let dir = PathBuf::from(sub_args.value_of("dir").expect("Has a default value"));
+    let mut config = match load_book_config(&dir) {
+        Ok((config, _)) => config,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            process::exit(1);
+        }
+    };
+    apply_args(&mut config, sub_args);
+
+    let mut preprocessors = toml::value::Table::new();
+    preprocessors.insert(
+        String::from(auto_gen_summary::PREPROCESSOR_NAME),
+        toml::Value::Table(config.to_table()),
+    );
+    let mut book_config = toml::value::Table::new();
+    book_config.insert(
+        String::from("preprocessor"),
+        toml::Value::Table(preprocessors),
+    );
+
+    match toml::to_string_pretty(&toml::Value::Table(book_config)) {
+        Ok(content) => print!("{}", content),
+        Err(e) => {
+            eprintln!("{:#}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn handle_compare(sub_args: &ArgMatches) -> ! {
+    let language_dirs: Vec<PathBuf> = sub_args
+        .values_of("dirs")
+        .expect("Required argument")
+        .map(PathBuf::from)
+        .collect();
+
+    let mut config = match load_book_config(&language_dirs[0]) {
+        Ok((config, _)) => config,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            process::exit(1);
+        }
+    };
+    apply_args(&mut config, sub_args);
+    config.read_only = true;
+
+    let missing_pages = match language::compare_language_roots(&language_dirs, &config) {
+        Ok(missing_pages) => missing_pages,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            process::exit(1);
+        }
+    };
+
+    exit_with_missing_pages(&missing_pages);
+}
+
+/// Compares the language roots of the book, after `gen` wrote their
+/// summaries
+fn check_translations(source_dir: &Path, config: &AutoGenConfig) -> ! {
+    if config.languages.len() < 2 {
+        eprintln!("Checking translations needs at least two languages.");
+        process::exit(1);
+    }
+
+    let language_dirs: Vec<PathBuf> = config
+        .languages
+        .iter()
+        .map(|language| source_dir.join(language))
+        .collect();
+    let mut config = config.clone();
+    config.read_only = true;
+
+    let missing_pages = match language::compare_language_roots(&language_dirs, &config) {
+        Ok(missing_pages) => missing_pages,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            process::exit(1);
+        }
+    };
+
+    exit_with_missing_pages(&missing_pages);
+}
+
+/// Prints the missing pages and exits with status 1 if there are any
+fn exit_with_missing_pages(missing_pages: &[language::MissingPages]) -> ! {
+    for missing in missing_pages {
+        println!(
+            "Pages in '{}' missing in '{}':\n  - {}",
+            missing.language_dir.display(),
+            missing.missing_in.display(),
+            missing.pages.join("\n  - ")
+        );
+    }
+
+    if missing_pages.is_empty() {
+        process::exit(0);
+    } else {
+        process::exit(1);
+    }
+}
+
+fn handle_migrate(sub_args: &ArgMatches) {
+    let dir = PathBuf::from(sub_args.value_of("dir").expect("Required argument"));
+    let (mut config, source_dir) = match load_book_config(&dir) {
+        Ok(book_config) => book_config,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            process::exit(1);
+        }
+    };
+    apply_args(&mut config, sub_args);
+
+    let migration = match migrate::migrate(&source_dir, &config) {
+        Ok(migration) => migration,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            process::exit(1);
+        }
+    };
+
+    for path in &migration.written_files {
+        println!("Wrote '{}'", path.display());
+    }
+
+    if !migration.unsupported.is_empty() {
+        eprintln!(
+            "Warn: The following entries can't be reproduced by the generator:\n  - {}",
+            migration.unsupported.join("\n  - ")
+        );
+    }
+}
+
+fn handle_lint(sub_args: &ArgMatches) -> ! {
+    let dir = PathBuf::from(sub_args.value_of("dir").expect("Required argument"));
+    let (mut config, source_dir) = match load_book_config(&dir) {
+        Ok(book_config) => book_config,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            process::exit(1);
+        }
+    };
+    apply_args(&mut config, sub_args);
+
+    let problems = match lint::lint(&source_dir, &config) {
+        Ok(problems) => problems,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            process::exit(1);
+        }
+    };
+
+    if sub_args.value_of("format") == Some("json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&problems).expect("Problems are serializable")
+        );
+    } else {
+        for problem in &problems {
+            println!("{}", problem);
+        }
+    }
+
+    if problems.is_empty() {
+        process::exit(0);
+    } else {
+        process::exit(1);
+    }
+}
+
 fn handle_supports(pre: &dyn Preprocessor, sub_args: &ArgMatches) -> ! {
     let renderer = sub_args.value_of("renderer").expect("Required argument");
-    let supported = pre.supports_renderer(&renderer);
+    let supported = pre.supports_renderer(renderer);
 
     if supported {
         process::exit(0);